@@ -0,0 +1,42 @@
+//! Times trajectory prediction around 64 planets, compare `cargo bench` with
+//! `cargo bench --features simd` to see the SIMD speedup.
+
+use std::time::Instant;
+
+use physics::*;
+
+const PLANETS: usize = 64;
+const STEPS: usize = 20_000;
+const RUNS: u32 = 10;
+
+fn main() {
+    let mut sim = Simulation::new(Config::new(1.0, STEPS as u64));
+    for i in 0..PLANETS {
+        let angle = (i as Real / PLANETS as Real * 360.0).to_radians();
+        let pos = AbiDVec2 {
+            x: 1e9 * angle.cos(),
+            y: 1e9 * angle.sin(),
+        };
+        sim.add_planet(Planet::new(1e22, 1e6, pos));
+    }
+    let mut craft = Craft::new();
+    craft.set_speed(AbiDVec2 { x: 10.0, y: 0.0 });
+    sim.add_craft(craft);
+
+    let mut best = f64::INFINITY;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        // Recomputes the whole prediction
+        sim.set_tick_time(1.0).unwrap();
+        best = best.min(start.elapsed().as_secs_f64());
+    }
+    let simd = if cfg!(feature = "simd") { "on" } else { "off" };
+    println!(
+        "{} planets, {} steps, simd {}: {:.2} ms, {:.1} ns per planet",
+        PLANETS,
+        STEPS,
+        simd,
+        best * 1e3,
+        best * 1e9 / (PLANETS * STEPS) as f64
+    );
+}
@@ -0,0 +1,60 @@
+use std::fmt;
+
+use wasm_bindgen::JsValue;
+
+/// Reasons a fallible simulation method can fail
+///
+/// Crosses into JS as an `Error` whose `name` is the variant name, so callers can tell the
+/// cases apart
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApogeeError {
+    /// No planet or craft exists at the given index
+    IndexOutOfBounds {
+        kind: &'static str,
+        index: usize,
+        len: usize,
+    },
+    /// A configuration value is outside of its valid range
+    InvalidConfig(&'static str),
+    /// Packed input data doesn't split into whole groups
+    InvalidPackedLength { len: usize, group: usize },
+    /// The craft isn't on a closed orbit around the planet
+    NotBound,
+}
+
+impl ApogeeError {
+    /// Name of the variant, used as the JS error's `name`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::IndexOutOfBounds { .. } => "IndexOutOfBounds",
+            Self::InvalidConfig(_) => "InvalidConfig",
+            Self::InvalidPackedLength { .. } => "InvalidPackedLength",
+            Self::NotBound => "NotBound",
+        }
+    }
+}
+
+impl fmt::Display for ApogeeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IndexOutOfBounds { kind, index, len } => {
+                write!(f, "{kind} index {index} out of bounds, there are {len}")
+            }
+            Self::InvalidConfig(reason) => write!(f, "invalid configuration: {reason}"),
+            Self::InvalidPackedLength { len, group } => {
+                write!(f, "packed data length {len} is not a multiple of {group}")
+            }
+            Self::NotBound => write!(f, "the craft is not on a closed orbit"),
+        }
+    }
+}
+
+impl std::error::Error for ApogeeError {}
+
+impl From<ApogeeError> for JsValue {
+    fn from(err: ApogeeError) -> Self {
+        let js_err = js_sys::Error::new(&err.to_string());
+        js_err.set_name(err.name());
+        js_err.into()
+    }
+}
@@ -1,15 +1,82 @@
+#[macro_use]
+mod log;
+
+mod error;
+mod math;
+#[cfg(feature = "presets")]
+mod presets;
+mod rng;
+#[cfg(feature = "simd")]
+mod simd;
 mod utils;
 
-use std::collections::VecDeque;
+use std::{
+    collections::VecDeque,
+    convert::Infallible,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
-use nalgebra::{Rotation2, Vector2};
+pub use error::ApogeeError;
+pub use log::LogLevel;
+use nalgebra::Vector2;
+use rng::Rng;
 use wasm_bindgen::prelude::*;
-use web_sys::console;
 
-type DVec2 = Vector2<f64>;
+/// Scalar the physics is computed in, `f32` with the `f32` feature for memory-constrained
+/// targets. JS sees numbers either way, but packed arrays become `Float32Array`s
+#[cfg(not(feature = "f32"))]
+pub type Real = f64;
+#[cfg(feature = "f32")]
+pub type Real = f32;
+#[cfg(feature = "f32")]
+use std::f32::consts;
+#[cfg(not(feature = "f32"))]
+use std::f64::consts;
+
+/// Simulation clock and the time tags on predicted states, in seconds. Double precision even
+/// with the `f32` feature, where ticks shorter than half an ulp of the clock would stop it
+pub type Time = f64;
+
+type DVec2 = Vector2<Real>;
+
+const G: Real = 6.67430e-11;
+const STANDARD_GRAVITY: Real = 9.80665;
+const SPEED_OF_LIGHT: Real = 299_792_458.0;
+// Rounded when built with the `f32` feature
+#[allow(clippy::excessive_precision)]
+const STEFAN_BOLTZMANN: Real = 5.670374419e-8;
 
-const G: f64 = 6.67430e-11;
-const STANDARD_GRAVITY: f64 = 9.80665;
+/// Temperature crafts start at and radiate down towards
+const AMBIENT_TEMPERATURE: Real = 290.0;
+/// Fraction of the kinetic energy flux of the oncoming air absorbed as heat
+const HEAT_TRANSFER_FRACTION: Real = 1e-3;
+/// Emissivity of the craft's hull for radiative cooling
+const HULL_EMISSIVITY: Real = 0.8;
+/// Specific heat capacity of the craft as a whole, in J/(kg·K)
+const CRAFT_SPECIFIC_HEAT: Real = 900.0;
+
+/// Largest angle the velocity may turn by in a single adaptive sampling substep
+const ADAPTIVE_MAX_TURN: Real = 0.01;
+/// Upper bound on the points stored per tick when adaptive sampling is enabled
+const ADAPTIVE_MAX_SUBSTEPS: u32 = 16;
+/// Most ticks a single [Simulation::tick_warp] call runs
+const MAX_WARP: u32 = 10_000;
+/// Most ticks [Simulation::tick_warp] runs while a craft is thrusting or flying through an
+/// atmosphere, since its state then changes quickly between ticks
+const PHYSICS_MAX_WARP: u32 = 4;
+/// Largest acceleration besides the central planet's pull, relative to it, for which an orbit is
+/// still drawn as a conic, see [Craft::conic_render_params]
+const CONIC_MAX_PERTURBATION: Real = 0.01;
+/// Most trajectory points reserved up front, longer predictions grow the buffer as they're
+/// computed instead
+const MAX_RESERVED_POINTS: usize = 1 << 20;
+/// Runs of consecutive segments [Craft::trajectory_is_closed] splits the trajectory into, each
+/// skipped at once when its bounding box misses the segment being tested
+const INTERSECTION_CHUNKS: usize = 64;
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
 // allocator.
@@ -20,297 +87,3254 @@ static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 #[wasm_bindgen(start)]
 pub fn main() {
     utils::set_panic_hook();
-    console::log_1(&"Done loading WASM blob".into());
+    info!("Done loading WASM blob");
 }
 
 #[wasm_bindgen]
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Clone, Copy)]
 pub struct Config {
-    tick_time: f64,
+    tick_time: Real,
     prediction_steps: u64,
+    /// Draws each predicted tick with up to [ADAPTIVE_MAX_SUBSTEPS] interpolated points depending
+    /// on how sharply the trajectory bends, so fast periapsis passes are drawn smoothly
+    pub adaptive_sampling: bool,
+    /// Adds the first-order general relativity correction to gravity, which makes orbits
+    /// precess like Mercury's
+    pub relativistic: bool,
+    /// Most steps a tick is split into when a craft's acceleration exceeds
+    /// [Config::substep_accel], so close passes on eccentric orbits stay accurate
+    pub max_substeps: u32,
+    /// Acceleration in m/s² per substep, a tick under an acceleration `a` is split into
+    /// `ceil(a / substep_accel)` steps. Zero disables substepping
+    pub substep_accel: Real,
+    /// Makes planets attract each other and move, with predictions propagating them alongside
+    /// every craft. Each predicted step then costs `O(planets²)` on top of the craft's own
+    /// `O(planets)` gravity, so leave it off for fixed planets
+    pub n_body: bool,
+    /// g₀ relating specific impulse to exhaust velocity in the rocket equation, in m/s².
+    /// Rescaled worlds can change it so `isp` values stay consistent with their gravity
+    pub standard_gravity: Real,
+    /// Meters per length unit in the positions, distances, velocities and accelerations
+    /// [Simulation]'s methods take and return, like `1000` to work in kilometers. [Craft] and
+    /// [Planet] objects always use SI units, as does the physics, including the copies
+    /// [Simulation::craft] and [Simulation::planet] return and their own methods
+    pub length_scale: Real,
+    /// Kilograms per mass unit in the masses [Simulation]'s methods take and return, like the
+    /// solar mass. Forces stay in newtons
+    pub mass_scale: Real,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            tick_time: 0.0,
+            prediction_steps: 0,
+            adaptive_sampling: false,
+            relativistic: false,
+            max_substeps: 0,
+            substep_accel: 0.0,
+            n_body: false,
+            standard_gravity: STANDARD_GRAVITY,
+            length_scale: 1.0,
+            mass_scale: 1.0,
+        }
+    }
 }
 
 #[wasm_bindgen]
 impl Config {
     #[wasm_bindgen(constructor)]
-    pub fn new(tick_time: f64, prediction_steps: u64) -> Self {
+    pub fn new(tick_time: Real, prediction_steps: u64) -> Self {
         Self {
             tick_time,
             prediction_steps,
+            ..Default::default()
         }
     }
 }
 
+impl Config {
+    /// SI length from one in [Config::length_scale] units
+    fn length_in(&self, length: Real) -> Real {
+        length * self.length_scale
+    }
+
+    /// Length in [Config::length_scale] units from an SI one
+    fn length_out(&self, length: Real) -> Real {
+        length / self.length_scale
+    }
+
+    /// [Config::length_in] for each component
+    fn vector_in(&self, vec: AbiDVec2) -> DVec2 {
+        DVec2::from(vec) * self.length_scale
+    }
+
+    /// [Config::length_out] for each component
+    fn vector_out(&self, vec: DVec2) -> AbiDVec2 {
+        (vec / self.length_scale).into()
+    }
+
+    /// SI mass from one in [Config::mass_scale] units
+    fn mass_in(&self, mass: Real) -> Real {
+        mass * self.mass_scale
+    }
+
+    /// Mass in [Config::mass_scale] units from an SI one
+    fn mass_out(&self, mass: Real) -> Real {
+        mass / self.mass_scale
+    }
+}
+
 #[wasm_bindgen]
 #[derive(Debug, Default)]
 pub struct Simulation {
     pub cfg: Config,
+    time: Time,
+    /// Ticks run to reach [Simulation::time]
+    tick_count: u64,
+    seed: u64,
+    rng: Rng,
+    planets: Vec<Planet>,
+    crafts: Vec<Craft>,
+    ephemeris: Ephemeris,
+    /// States before the most recent ticks, newest last
+    history: VecDeque<TickState>,
+    undo_depth: usize,
+    cancel: CancelToken,
+    on_max_q: Option<js_sys::Function>,
+    on_tick: Option<js_sys::Function>,
+    on_entry_interface: Option<js_sys::Function>,
+}
+
+/// Flag shared with a [Simulation] to stop its long-running computations early
+///
+/// Since a running computation blocks the main thread, the token is meant to be cancelled from
+/// inside a JS callback it invokes, or from a worker sharing the module's memory. The methods
+/// honoring cancellation are [Simulation::tick_many], [Simulation::tick_warp],
+/// [Simulation::ensemble_closest_approach], [Simulation::divergence] and
+/// [Simulation::recompute_trajectories_with_progress]; each of them clears the flag when it
+/// starts
+#[wasm_bindgen]
+#[derive(Debug, Default, Clone)]
+pub struct CancelToken {
+    flag: Arc<AtomicBool>,
+}
+
+#[wasm_bindgen]
+impl CancelToken {
+    /// Asks the computation currently running to stop at the next opportunity
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn cancelled(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+}
+
+impl CancelToken {
+    fn reset(&self) {
+        self.flag.store(false, Ordering::Relaxed);
+    }
+}
+
+/// What a tick changes, kept around to undo it
+#[derive(Debug)]
+struct TickState {
+    time: Time,
+    tick_count: u64,
     planets: Vec<Planet>,
     crafts: Vec<Craft>,
 }
 
+/// Planets at every tick from a starting time on, computed once and shared by all the
+/// predictions so [Config::n_body] motion isn't integrated again for each craft
+#[derive(Debug)]
+struct Ephemeris {
+    time: Time,
+    /// [Config::tick_time] the frames are spaced by, they're dropped when [Simulation::cfg]
+    /// changes it
+    tick_time: Real,
+    frames: VecDeque<Vec<Planet>>,
+}
+
+impl Default for Ephemeris {
+    fn default() -> Self {
+        Self::new(&[], 0.0)
+    }
+}
+
+impl Ephemeris {
+    fn new(planets: &[Planet], time: Time) -> Self {
+        Self {
+            time,
+            tick_time: 0.0,
+            frames: VecDeque::from([planets.to_vec()]),
+        }
+    }
+
+    /// Planets at the given tick-aligned time, integrating them up to it if needed
+    fn at(&mut self, cfg: &Config, time: Time) -> &[Planet] {
+        if !cfg.n_body {
+            self.frames.truncate(1);
+            return &self.frames[0];
+        }
+        if self.tick_time != cfg.tick_time {
+            // Only the first frame doesn't depend on the tick time
+            self.frames.truncate(1);
+            self.tick_time = cfg.tick_time;
+        }
+        let index = ((time - self.time) / cfg.tick_time as Time).round() as usize;
+        while self.frames.len() <= index {
+            let mut next = self.frames.back().unwrap().clone();
+            step_planets(&mut next, cfg);
+            self.frames.push_back(next);
+        }
+        &self.frames[index]
+    }
+
+    /// Moves the start one tick forward, dropping the frame left behind
+    fn advance(&mut self, cfg: &Config) {
+        let next = self.time + cfg.tick_time as Time;
+        if cfg.n_body {
+            self.at(cfg, next);
+            self.frames.pop_front();
+        }
+        self.time = next;
+    }
+}
+
 #[wasm_bindgen]
 impl Simulation {
     #[wasm_bindgen(constructor)]
     pub fn new(cfg: Config) -> Self {
         Self {
             cfg,
+            undo_depth: 1,
             ..Default::default()
         }
     }
 
+    /// Creates a simulation with room for the given number of planets and crafts, so adding them
+    /// doesn't reallocate
+    pub fn with_capacity(cfg: Config, planets: usize, crafts: usize) -> Self {
+        Self {
+            planets: Vec::with_capacity(planets),
+            crafts: Vec::with_capacity(crafts),
+            ..Self::new(cfg)
+        }
+    }
+
     /// Adds a planet to the simulation and recomputes the ships' trajectory
     pub fn add_planet(&mut self, planet: Planet) {
         self.planets.push(planet);
-        self.recompute_craft_trajectories()
+        self.planets_changed();
     }
 
-    /// Adds a spacecraft to the simulation
-    pub fn add_craft(&mut self, craft: Craft) {
-        self.crafts.push(craft);
+    /// Moves a planet, recomputing the ships' trajectories
+    pub fn move_planet(&mut self, index: usize, pos: AbiDVec2) -> Result<(), ApogeeError> {
+        let pos = self.cfg.vector_in(pos);
+        self.edit_planet(index, |p| p.position = pos)
     }
 
-    /// Advances the simulation by the configured delta-time
-    pub fn tick(&mut self) {
-        for craft in self.crafts.iter_mut() {
-            if craft.throttle == 0.0 {
-                craft.populate_trajectory(
-                    &self.planets,
-                    self.cfg.tick_time,
-                    self.cfg.prediction_steps + 1,
-                );
-                (craft.speed, craft.position) = craft.trajectory.pop_front().unwrap().into();
-            } else {
-                let accel: DVec2 = self
-                    .planets
-                    .iter()
-                    .map(|p| p.gravity_accel_on(craft.position))
-                    .fold(craft.accel_vector(), |f1, f2| f1 + f2);
-                craft.speed += accel * self.cfg.tick_time;
-                craft.position += craft.speed * self.cfg.tick_time;
-                craft.consume_fuel(self.cfg.tick_time);
-
-                craft.trajectory.clear();
-                craft.populate_trajectory(
-                    &self.planets,
-                    self.cfg.tick_time,
-                    self.cfg.prediction_steps,
-                );
-            }
-        }
+    /// Changes a planet's mass, recomputing the ships' trajectories
+    pub fn set_planet_mass(&mut self, index: usize, mass: Real) -> Result<(), ApogeeError> {
+        let mass = self.cfg.mass_in(mass);
+        self.edit_planet(index, |p| p.mass = mass)
     }
 
-    pub fn set_tick_time(&mut self, tick_time: f64) {
-        self.cfg.tick_time = tick_time;
-        self.recompute_craft_trajectories()
+    /// Changes a planet's equatorial radius, recomputing the ships' trajectories
+    pub fn set_planet_radius(&mut self, index: usize, radius: Real) -> Result<(), ApogeeError> {
+        let radius = self.cfg.length_in(radius);
+        self.edit_planet(index, |p| p.radius = radius)
     }
 
-    fn recompute_craft_trajectories(&mut self) {
-        for craft in &mut self.crafts {
-            craft.trajectory.clear();
-            craft.populate_trajectory(&self.planets, self.cfg.tick_time, self.cfg.prediction_steps)
-        }
+    /// Changes a craft's dry mass, recomputing its trajectory
+    pub fn set_craft_dry_mass(&mut self, index: usize, dry_mass: Real) -> Result<(), ApogeeError> {
+        let dry_mass = self.cfg.mass_in(dry_mass);
+        self.edit_craft(index, |c| c.dry_mass = dry_mass)
     }
-}
 
-/// Exerts gravity on [Craft]s
-#[wasm_bindgen]
-#[derive(Debug, Default)]
-pub struct Planet {
-    pub mass: f64,
-    pub radius: f64,
-    position: DVec2,
-}
+    /// Changes a craft's engine thrust, recomputing its trajectory
+    pub fn set_craft_thrust(&mut self, index: usize, thrust: Real) -> Result<(), ApogeeError> {
+        self.edit_craft(index, |c| c.thrust = thrust)
+    }
 
-#[wasm_bindgen]
-impl Planet {
-    #[wasm_bindgen(constructor)]
-    pub fn new(mass: f64, radius: f64, pos: AbiDVec2) -> Self {
-        Self {
-            mass,
-            radius,
-            position: pos.into(),
+    /// Changes a craft's engine specific impulse, recomputing its trajectory
+    pub fn set_craft_isp(&mut self, index: usize, isp: Real) -> Result<(), ApogeeError> {
+        self.edit_craft(index, |c| c.isp = isp)
+    }
+
+    /// Commands a craft's throttle, taking effect from the next tick
+    pub fn set_craft_throttle(&mut self, index: usize, throttle: Real) -> Result<(), ApogeeError> {
+        let index = self.craft_index(index)?;
+        let craft = &mut self.crafts[index];
+        craft.set_throttle(throttle);
+        let steps = craft.prediction_steps(&self.cfg);
+        craft.populate_powered_trajectory(&mut self.ephemeris, &self.cfg, self.time, steps);
+        Ok(())
+    }
+
+    /// Places a craft on a planet's surface in the direction `surface_angle` radians
+    /// counterclockwise from the x axis, pointing straight up and moving along with the ground.
+    /// Nothing holds it there, so it needs to thrust on the next tick unless its
+    /// [Craft::collision_mode] lands it
+    pub fn launch_from_surface(
+        &mut self,
+        craft_index: usize,
+        planet_index: usize,
+        surface_angle: Real,
+    ) -> Result<(), ApogeeError> {
+        let planet = &self.planets[self.planet_index(planet_index)?];
+        let direction = DVec2::new(math::cos(surface_angle), math::sin(surface_angle));
+        let (surface, normal) = planet.surface_below(planet.position + direction);
+        let velocity = planet.surface_velocity(surface);
+        self.edit_craft(craft_index, |c| {
+            c.position = surface;
+            c.speed = velocity;
+            c.set_heading(math::atan2(normal.y, normal.x));
+        })
+    }
+
+    /// Calls [Craft::mark_fuel] on a craft in the simulation, [Simulation::craft] only returns
+    /// copies
+    pub fn mark_craft_fuel(&mut self, index: usize) -> Result<(), ApogeeError> {
+        let index = self.craft_index(index)?;
+        self.crafts[index].mark_fuel();
+        Ok(())
+    }
+
+    /// Turns a craft's [Craft::predict] on or off, recomputing or dropping its trajectory
+    pub fn set_craft_predict(&mut self, index: usize, predict: bool) -> Result<(), ApogeeError> {
+        self.edit_craft(index, |c| c.set_predict(predict))
+    }
+
+    /// Makes a craft ignore a planet or interact with it again, recomputing its trajectory
+    pub fn set_planet_ignored(
+        &mut self,
+        craft_index: usize,
+        planet_index: usize,
+        ignored: bool,
+    ) -> Result<(), ApogeeError> {
+        let planet_index = self.planet_index(planet_index)?;
+        self.edit_craft(craft_index, |c| c.set_planet_ignored(planet_index, ignored))
+    }
+
+    /// Points a craft, taking effect from the next tick
+    pub fn set_craft_heading(&mut self, index: usize, heading: Real) -> Result<(), ApogeeError> {
+        let index = self.craft_index(index)?;
+        let craft = &mut self.crafts[index];
+        craft.set_heading(heading);
+        // Turning doesn't change a coasting prediction besides the heading it carries, which
+        // the other modes steer away from on the next tick anyway
+        if matches!(
+            craft.heading_mode,
+            HeadingMode::Fixed | HeadingMode::TargetRelative
+        ) {
+            for vp in &mut craft.trajectory {
+                vp.heading = craft.heading;
+            }
         }
+        let steps = craft.prediction_steps(&self.cfg);
+        craft.populate_powered_trajectory(&mut self.ephemeris, &self.cfg, self.time, steps);
+        Ok(())
     }
 
-    #[wasm_bindgen(getter)]
-    pub fn position(&self) -> AbiDVec2 {
-        self.position.into()
+    /// Adds a spacecraft to the simulation
+    pub fn add_craft(&mut self, mut craft: Craft) {
+        craft.reserve_trajectory(&self.cfg);
+        self.crafts.push(craft);
+        self.history.clear();
     }
-}
 
-impl Planet {
-    /// Computes the gravitational acceleration applied on an object of negligible mass
-    fn gravity_accel_on(&self, pos: DVec2) -> DVec2 {
-        let mut dist = self.position - pos;
-        let accel_mod = self.mass * G / dist.magnitude().powi(2);
-        dist.set_magnitude(accel_mod);
-        dist
+    /// Sets the verbosity of the module's diagnostics, shared by every simulation
+    pub fn set_log_level(&self, level: LogLevel) {
+        log::set_level(level);
     }
-}
 
-// How do I pass this stuff by value to JS
-#[wasm_bindgen]
-#[derive(Debug, Default, Clone, Copy)]
-pub struct AbiDVec2 {
-    pub x: f64,
-    pub y: f64,
-}
+    /// Token that cancels this simulation's long-running computations, see [CancelToken]
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
 
-impl From<DVec2> for AbiDVec2 {
-    fn from(vec: DVec2) -> Self {
-        Self { x: vec.x, y: vec.y }
+    /// Asks the computation currently running to stop, same as [CancelToken::cancel]
+    pub fn request_cancel(&self) {
+        self.cancel.cancel();
     }
-}
 
-impl From<AbiDVec2> for DVec2 {
-    fn from(vec: AbiDVec2) -> Self {
-        DVec2::new(vec.x, vec.y)
+    /// Gravitational acceleration each planet applies on a craft at its current position, packed
+    /// as `[ax, ay]` pairs in planet order in a `Float64Array`. They sum to the total gravity,
+    /// planets the craft ignores get `[0, 0]`
+    pub fn gravity_breakdown(&self, craft_index: usize) -> Result<Vec<Real>, ApogeeError> {
+        let craft = &self.crafts[self.craft_index(craft_index)?];
+        Ok(self
+            .planets
+            .iter()
+            .enumerate()
+            .flat_map(|(i, p)| {
+                if craft.ignores(i) {
+                    return [0.0, 0.0];
+                }
+                let accel = gravity_accel(
+                    std::slice::from_ref(p),
+                    &[],
+                    &self.cfg,
+                    craft.position,
+                    craft.speed,
+                ) / self.cfg.length_scale;
+                [accel.x, accel.y]
+            })
+            .collect())
     }
-}
 
-#[derive(Debug, Default, Clone, Copy)]
-pub struct VelPos {
-    pub vel: DVec2,
-    pub pos: DVec2,
-}
+    /// Newtonian gravitational acceleration at any point, where points inside a planet feel its
+    /// surface gravity instead of the singularity at its center
+    pub fn gravity_at(&self, pos: AbiDVec2) -> AbiDVec2 {
+        let pos = self.cfg.vector_in(pos);
+        let accel = self
+            .planets
+            .iter()
+            .map(|p| {
+                let offset = p.position - pos;
+                let distance = offset.magnitude();
+                if distance == 0.0 {
+                    return DVec2::zeros();
+                }
+                offset * (p.mu() / (distance * math::powi(distance.max(p.radius), 2)))
+            })
+            .fold(DVec2::zeros(), |a, b| a + b);
+        self.cfg.vector_out(accel)
+    }
 
-impl From<VelPos> for (DVec2, DVec2) {
-    fn from(value: VelPos) -> Self {
-        (value.vel.into(), value.pos.into())
+    /// Mass-weighted average position of the planets, crafts are too light to move it
+    pub fn barycenter(&self) -> AbiDVec2 {
+        self.cfg.vector_out(self.mass_weighted(|p| p.position))
     }
-}
 
-/// Represents a spacecraft propelled by a reaction motor
-#[wasm_bindgen]
-#[derive(Debug, Default)]
-pub struct Craft {
-    pub dry_mass: f64,
-    pub fuel_mass: f64,
-    pub isp: f64,
-    pub thrust: f64,
-    position: DVec2,
-    speed: DVec2,
-    pub heading: f64,
-    pub throttle: f64,
-    trajectory: VecDeque<VelPos>,
-}
+    /// Velocity the planets' barycenter drifts at in [Config::n_body] mode
+    pub fn barycenter_velocity(&self) -> AbiDVec2 {
+        self.cfg.vector_out(self.mass_weighted(|p| p.velocity))
+    }
 
-#[wasm_bindgen]
-impl Craft {
-    #[wasm_bindgen(constructor)]
-    pub fn new() -> Self {
-        Default::default()
+    /// Positions of every craft and, if `include_planets`, then every planet relative to the
+    /// [Simulation::barycenter], packed as `[x, y]` pairs in a `Float64Array`
+    pub fn barycentric_positions(&self, include_planets: bool) -> Vec<Real> {
+        let barycenter = self.mass_weighted(|p| p.position);
+        self.packed_states(include_planets, |c| c.position, |p| p.position)
+            .flat_map(|pos| {
+                let pos = self.cfg.vector_out(pos - barycenter);
+                [pos.x, pos.y]
+            })
+            .collect()
     }
 
-    #[wasm_bindgen(getter)]
-    pub fn position(&self) -> AbiDVec2 {
-        self.position.into()
+    /// Velocities in the same order as [Simulation::barycentric_positions], with the barycenter's
+    /// drift subtracted if `remove_drift`
+    pub fn barycentric_velocities(&self, include_planets: bool, remove_drift: bool) -> Vec<Real> {
+        let drift = if remove_drift {
+            self.mass_weighted(|p| p.velocity)
+        } else {
+            DVec2::zeros()
+        };
+        self.packed_states(include_planets, |c| c.speed, |p| p.velocity)
+            .flat_map(|vel| {
+                let vel = self.cfg.vector_out(vel - drift);
+                [vel.x, vel.y]
+            })
+            .collect()
     }
 
-    #[wasm_bindgen(setter)]
-    pub fn set_position(&mut self, pos: AbiDVec2) {
-        self.position = pos.into()
+    /// Copy of a planet, to read its current state
+    pub fn planet(&self, index: usize) -> Result<Planet, ApogeeError> {
+        Ok(self.planets[self.planet_index(index)?].clone())
     }
 
-    #[wasm_bindgen(getter)]
-    pub fn speed(&self) -> AbiDVec2 {
-        self.speed.into()
+    /// Index of the first craft with the given [Craft::name]
+    pub fn craft_by_name(&self, name: &str) -> Option<usize> {
+        self.crafts
+            .iter()
+            .position(|c| c.name.as_deref() == Some(name))
     }
 
-    #[wasm_bindgen(setter)]
-    pub fn set_speed(&mut self, vel: AbiDVec2) {
-        self.speed = vel.into()
+    /// A craft's delta-v using the configured [Config::standard_gravity]
+    pub fn deltav(&self, craft_index: usize) -> Result<Real, ApogeeError> {
+        let craft = &self.crafts[self.craft_index(craft_index)?];
+        Ok(self
+            .cfg
+            .length_out(craft.deltav_with(self.cfg.standard_gravity)))
     }
 
-    /// Computes the craft's delta-v
-    pub fn deltav(&self) -> f64 {
-        let exhaust_vel = self.isp * STANDARD_GRAVITY;
-        let mass_ratio = self.mass() / self.dry_mass;
-        exhaust_vel * mass_ratio.ln()
+    /// Copy of a craft, to read its current state
+    pub fn craft(&self, index: usize) -> Result<Craft, ApogeeError> {
+        Ok(self.crafts[self.craft_index(index)?].clone())
     }
 
-    // Not JS iterator compliant but should be good enough?
-    pub fn trajectory_iter(&self) -> TrajectoryIter {
-        TrajectoryIter {
-            inner: &self.trajectory as *const _,
-        }
+    /// Sets a JS closure called during [Simulation::tick] when a craft passes its maximum dynamic
+    /// pressure, with the craft index and the peak pressure in pascals. `null` removes it
+    pub fn on_max_q(&mut self, f: Option<js_sys::Function>) {
+        self.on_max_q = f;
     }
-}
 
-/// Can call a JS closure over items in the deque
-#[wasm_bindgen]
-pub struct TrajectoryIter {
-    inner: *const VecDeque<VelPos>,
-}
+    /// Sets a JS closure called at the end of every [Simulation::tick] with the new time and the
+    /// telemetry of the live crafts in a `Float64Array`, packed as consecutive
+    /// `[craft_index, x, y, vx, vy, effective_throttle, fuel_mass, ax, ay]` groups where the
+    /// acceleration is the average over the tick. `null` removes it
+    pub fn on_tick(&mut self, f: Option<js_sys::Function>) {
+        self.on_tick = f;
+    }
 
-#[wasm_bindgen]
-impl TrajectoryIter {
-    /// Calls the provided JS closure for each element in the buffer
-    ///
-    /// The closure takes x and y coordinates, if any exception is caught the loop is stopped and
-    /// the error is returned
-    pub fn each_position(&self, f: &js_sys::Function) -> Result<(), JsValue> {
-        let this = JsValue::null();
-        for elem in unsafe { &*self.inner } {
-            f.call2(
-                &this,
-                &JsValue::from(elem.pos.x),
-                &JsValue::from(elem.pos.y),
-            )?;
+    /// Sets a JS closure called during [Simulation::tick] when a craft descends through a
+    /// planet's [Planet::atmosphere_interface], with the craft and planet indices. `null`
+    /// removes it
+    pub fn on_entry_interface(&mut self, f: Option<js_sys::Function>) {
+        self.on_entry_interface = f;
+    }
+
+    /// Sets how many ticks can be undone, one by default
+    pub fn set_undo_depth(&mut self, depth: usize) {
+        self.undo_depth = depth;
+        while self.history.len() > depth {
+            self.history.pop_front();
         }
-        Ok(())
     }
-}
 
-impl Craft {
-    /// Total craft mass
-    fn mass(&self) -> f64 {
-        self.dry_mass + self.fuel_mass
+    /// Reverts the most recent tick, returning whether there was one to revert
+    ///
+    /// Adding or editing planets and adding crafts clears the undo history
+    pub fn undo(&mut self) -> bool {
+        let Some(state) = self.history.pop_back() else {
+            return false;
+        };
+        self.time = state.time;
+        self.tick_count = state.tick_count;
+        self.planets = state.planets;
+        self.crafts = state.crafts;
+        self.reset_ephemeris();
+        self.recompute_craft_trajectories();
+        true
     }
 
-    /// Returns the craft's acceleration vector
-    fn accel_vector(&self) -> DVec2 {
-        if self.fuel_mass == 0.0 {
-            return DVec2::new(0.0, 0.0);
-        }
+    /// Adds planets packed as consecutive `[mass, radius, x, y]` groups, recomputing the ships'
+    /// trajectory once at the end
+    pub fn add_planets_packed(&mut self, data: &[Real]) -> Result<(), ApogeeError> {
+        let groups = packed_groups(data, PLANET_GROUP_LEN)?;
+        let cfg = &self.cfg;
+        self.planets.extend(groups.map(|g| Planet {
+            mass: cfg.mass_in(g[0]),
+            radius: cfg.length_in(g[1]),
+            position: cfg.vector_in(AbiDVec2 { x: g[2], y: g[3] }),
+            ..Default::default()
+        }));
+        self.planets_changed();
+        Ok(())
+    }
 
-        let thrust = self.thrust * self.throttle;
-        Rotation2::new(self.heading) * Vector2::new(thrust / self.mass(), 0.0)
+    /// Adds spacecraft packed as consecutive
+    /// `[dry_mass, fuel_mass, isp, thrust, x, y, vx, vy, heading]` groups
+    pub fn add_crafts_packed(&mut self, data: &[Real]) -> Result<(), ApogeeError> {
+        let groups = packed_groups(data, CRAFT_GROUP_LEN)?;
+        let cfg = &self.cfg;
+        self.crafts.extend(groups.map(|g| {
+            let mut craft = Craft {
+                dry_mass: cfg.mass_in(g[0]),
+                fuel_mass: cfg.mass_in(g[1]),
+                isp: g[2],
+                thrust: g[3],
+                position: cfg.vector_in(AbiDVec2 { x: g[4], y: g[5] }),
+                speed: cfg.vector_in(AbiDVec2 { x: g[6], y: g[7] }),
+                heading: normalize_angle(g[8]),
+                ..Default::default()
+            };
+            craft.reserve_trajectory(cfg);
+            craft
+        }));
+        self.history.clear();
+        self.recompute_craft_trajectories();
+        Ok(())
     }
 
-    /// Compute the consumed fuel from the expended delta-v in the given time
-    fn consume_fuel(&mut self, time: f64) {
-        // flow_rate = F / (g_0 * Isp)
+    /// Elapsed simulation time in seconds
+    #[wasm_bindgen(getter)]
+    pub fn time(&self) -> Time {
+        self.time
+    }
 
-        let force = self.thrust * self.throttle;
-        let exhaust_velocity = self.isp * STANDARD_GRAVITY;
-        let flow_rate = dbg!(force) / dbg!(exhaust_velocity);
+    /// Number of ticks run so far, counting those of [Simulation::tick_many] and
+    /// [Simulation::tick_warp] and going back with [Simulation::undo] like [Simulation::time].
+    /// Unlike the time it doesn't accumulate rounding errors, so it can key events to exact steps
+    #[wasm_bindgen(getter)]
+    pub fn tick_count(&self) -> u64 {
+        self.tick_count
+    }
 
-        self.fuel_mass = (self.fuel_mass - flow_rate * time).max(0.0)
+    /// Advances the simulation by `steps` ticks, returning how many were performed before being
+    /// cancelled
+    pub fn tick_many(&mut self, steps: u32) -> u32 {
+        self.cancel.reset();
+        for done in 0..steps {
+            if self.cancel.cancelled() {
+                return done;
+            }
+            self.tick();
+        }
+        steps
     }
 
-    /// Computes or extends the current trajectory
-    fn populate_trajectory(&mut self, planets: &[Planet], timestep: f64, len: u64) {
-        let start = if let Some(vp) = self.trajectory.back() {
-            (*vp).into()
-        } else {
-            (self.speed, self.position)
-        };
-        let iter = std::iter::successors(Some(start), |(mut speed, position)| {
-            let accel = planets
-                .iter()
-                .map(|p| p.gravity_accel_on(*position))
-                .fold(Vector2::new(0.0, 0.0), |a, b| a + b);
-            speed += accel * timestep;
-            Some((speed, *position + speed * timestep))
-        })
-        .map(|(vel, pos)| VelPos {
-            vel: vel.into(),
-            pos: pos.into(),
-        })
-        .take(len as usize - self.trajectory.len());
+    /// Advances the simulation by `warp` ticks of the configured delta-time, returning how many
+    /// were run
+    ///
+    /// The warp is capped at [MAX_WARP], and at [PHYSICS_MAX_WARP] as soon as a craft is
+    /// thrusting or inside an atmosphere, so fast warps don't skip over a burn or an entry.
+    /// Cancellation stops it early like [Simulation::tick_many]
+    pub fn tick_warp(&mut self, warp: u32) -> u32 {
+        self.cancel.reset();
+        let warp = warp.min(MAX_WARP);
+        for done in 0..warp {
+            if self.cancel.cancelled() || (done >= PHYSICS_MAX_WARP && self.needs_physics_warp()) {
+                return done;
+            }
+            self.tick();
+        }
+        warp
+    }
+
+    /// Advances the simulation by the configured delta-time
+    pub fn tick(&mut self) {
+        // Turning n-body mode off through the config stops the planets
+        if self.stop_planets() {
+            self.reset_ephemeris();
+            self.recompute_craft_trajectories();
+        }
+        self.save_undo_state();
+        self.update_headings();
+        let next = self.time + self.cfg.tick_time as Time;
+        let mut max_q_events = Vec::new();
+        let mut entry_events = Vec::new();
+        for (i, craft) in self.crafts.iter_mut().enumerate() {
+            if craft.destroyed {
+                continue;
+            }
+            craft.spool_throttle(self.cfg.tick_time);
+            let (position, speed) = (craft.position, craft.speed);
+            // Free fall feels weightless, only thrust and drag load the craft
+            craft.applied_acceleration = craft.accel_vector()
+                + drag_accel(
+                    &self.planets,
+                    &craft.ignored_planets,
+                    craft.drag_factor(),
+                    craft.position,
+                    craft.speed,
+                );
+            if craft.effective_throttle == 0.0 {
+                let state = VelPos {
+                    vel: craft.speed,
+                    pos: craft.position,
+                    time: self.time,
+                    heading: craft.heading,
+                };
+                // Computed the same way as the prediction, which is only kept for display
+                let state = coast_step(
+                    &self.planets,
+                    &craft.ignored_planets,
+                    &self.cfg,
+                    craft.drag_factor(),
+                    state,
+                );
+                (craft.speed, craft.position) = state.into();
+                // Adaptive sampling may have stored intermediate points before the next tick
+                let epsilon = self.cfg.tick_time as Time * 1e-3;
+                while craft
+                    .trajectory
+                    .front()
+                    .is_some_and(|vp| vp.time <= next + epsilon)
+                {
+                    craft.trajectory.pop_front();
+                }
+                while craft.apsides.front().is_some_and(|a| a.time <= next) {
+                    craft.apsides.pop_front();
+                }
+                let steps = craft.prediction_steps(&self.cfg);
+                craft.populate_trajectory(&mut self.ephemeris, &self.cfg, next, steps);
+                // Points past the horizon may be left from extending it with trajectory_chunk,
+                // drop them so the horizon matches the thrusting branch's
+                craft.truncate_trajectory(
+                    next + steps as Time * self.cfg.tick_time as Time + epsilon,
+                );
+            } else {
+                let state = VelPos {
+                    vel: craft.speed,
+                    pos: craft.position,
+                    time: self.time,
+                    heading: craft.heading,
+                };
+                let state = tick_step(
+                    self.ephemeris.at(&self.cfg, self.time),
+                    &craft.ignored_planets,
+                    &self.cfg,
+                    craft.drag_factor(),
+                    state,
+                    craft.accel_vector(),
+                );
+                (craft.speed, craft.position) = state.into();
+                craft.consume_fuel(self.cfg.tick_time, self.cfg.standard_gravity);
 
-        self.trajectory.extend(iter);
+                craft.clear_trajectory();
+                craft.populate_trajectory(
+                    &mut self.ephemeris,
+                    &self.cfg,
+                    next,
+                    craft.prediction_steps(&self.cfg),
+                );
+            }
+            let steps = craft.prediction_steps(&self.cfg);
+            craft.populate_powered_trajectory(&mut self.ephemeris, &self.cfg, next, steps);
+
+            craft.last_acceleration = (craft.speed - speed) / self.cfg.tick_time;
+
+            // The craft has moved to the end of the tick, and so have the planets in n-body mode
+            craft.exchange_heat(self.ephemeris.at(&self.cfg, next), self.cfg.tick_time);
+            if craft.max_temperature > 0.0 && craft.temperature() > craft.max_temperature {
+                craft.destroyed = true;
+                craft.clear_trajectory();
+                warn!(
+                    "craft overheated to {} K and was destroyed",
+                    craft.temperature()
+                );
+            }
+            let (planets, previous) = (self.ephemeris.at(&self.cfg, next), &self.planets);
+            entry_events.extend(
+                (0..planets.len())
+                    .filter(|&j| {
+                        !craft.ignores(j)
+                            && previous[j].above_interface(position)
+                            && !planets[j].above_interface(craft.position)
+                    })
+                    .map(|j| (i, j)),
+            );
+            let hit = (0..planets.len())
+                .find(|&i| !craft.ignores(i) && planets[i].altitude_of(craft.position) < 0.0);
+            if let Some(index) = hit.filter(|_| craft.collision_mode != CollisionMode::PassThrough)
+            {
+                let planet = planets[index].clone();
+                let (surface, normal) = planet.surface_below(craft.position);
+                let ground_velocity = planet.surface_velocity(surface);
+                match craft.collision_mode {
+                    CollisionMode::Destroy => {
+                        craft.destroyed = true;
+                        craft.clear_trajectory();
+                        warn!("craft crashed into planet {}", index);
+                    }
+                    CollisionMode::Stop => {
+                        craft.position = surface;
+                        craft.speed = ground_velocity;
+                    }
+                    CollisionMode::Bounce => {
+                        let relative = craft.speed - ground_velocity;
+                        let normal_speed = relative.dot(&normal).min(0.0);
+                        craft.position = surface;
+                        craft.speed -= normal * normal_speed * (1.0 + craft.restitution);
+                    }
+                    CollisionMode::PassThrough => unreachable!(),
+                }
+                if !craft.destroyed {
+                    craft.clear_trajectory();
+                    craft.populate_trajectory(
+                        &mut self.ephemeris,
+                        &self.cfg,
+                        next,
+                        craft.prediction_steps(&self.cfg),
+                    );
+                }
+            }
+            if let Some(max_q) = craft.track_dynamic_pressure(self.ephemeris.at(&self.cfg, next)) {
+                max_q_events.push((i, max_q));
+            }
+        }
+        self.time = next;
+        self.tick_count += 1;
+        self.ephemeris.advance(&self.cfg);
+        self.planets = self.ephemeris.at(&self.cfg, next).to_vec();
+
+        if let Some(f) = &self.on_max_q {
+            for (i, max_q) in max_q_events {
+                if let Err(err) = f.call2(&JsValue::null(), &JsValue::from(i as u32), &max_q.into())
+                {
+                    error!("max-Q callback failed: {:?}", err);
+                }
+            }
+        }
+        if let Some(f) = &self.on_entry_interface {
+            for (craft, planet) in entry_events {
+                let (craft, planet) = (JsValue::from(craft as u32), JsValue::from(planet as u32));
+                if let Err(err) = f.call2(&JsValue::null(), &craft, &planet) {
+                    error!("entry interface callback failed: {:?}", err);
+                }
+            }
+        }
+        if let Some(f) = &self.on_tick {
+            let cfg = &self.cfg;
+            let telemetry: Vec<Real> = self
+                .crafts
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| !c.destroyed)
+                .flat_map(|(i, c)| {
+                    let (pos, vel) = (cfg.vector_out(c.position), cfg.vector_out(c.speed));
+                    let accel = cfg.vector_out(c.last_acceleration);
+                    [
+                        i as Real,
+                        pos.x,
+                        pos.y,
+                        vel.x,
+                        vel.y,
+                        c.effective_throttle,
+                        cfg.mass_out(c.fuel_mass),
+                        accel.x,
+                        accel.y,
+                    ]
+                })
+                .collect();
+            if let Err(err) = f.call2(&JsValue::null(), &self.time.into(), &telemetry.into()) {
+                error!("tick callback failed: {:?}", err);
+            }
+        }
+    }
+
+    pub fn set_tick_time(&mut self, tick_time: Real) -> Result<(), ApogeeError> {
+        check_tick_time(tick_time)?;
+        self.cfg.tick_time = tick_time;
+        self.reset_ephemeris();
+        self.recompute_craft_trajectories();
+        Ok(())
+    }
+
+    /// Returns `count` trajectory points of a craft starting at index `start`, packed as
+    /// `[x0, y0, x1, y1, ...]` in a `Float64Array`
+    ///
+    /// The craft's trajectory is extended past the prediction horizon as needed, and the extra
+    /// points are kept so later calls don't compute them again. The points are only evenly
+    /// spaced in time without [Config::adaptive_sampling], see [Simulation::trajectory_time_chunk]
+    ///
+    /// Fails with [ApogeeError::InvalidConfig] if the tick time isn't positive, which
+    /// [Config::new] doesn't check, since the prediction then never advances
+    pub fn trajectory_chunk(
+        &mut self,
+        craft_index: usize,
+        start: usize,
+        count: usize,
+    ) -> Result<Vec<Real>, ApogeeError> {
+        let cfg = self.cfg;
+        Ok(self
+            .extended_trajectory(craft_index, start + count)?
+            .range(start..start + count)
+            .flat_map(|vp| [vp.pos.x, vp.pos.y])
+            .map(|x| cfg.length_out(x))
+            .collect())
+    }
+
+    /// Simulation times the points returned by [Simulation::trajectory_chunk] are predicted for,
+    /// in a `Float64Array`
+    pub fn trajectory_time_chunk(
+        &mut self,
+        craft_index: usize,
+        start: usize,
+        count: usize,
+    ) -> Result<Vec<Time>, ApogeeError> {
+        Ok(self
+            .extended_trajectory(craft_index, start + count)?
+            .range(start..start + count)
+            .map(|vp| vp.time)
+            .collect())
+    }
+
+    /// Heading in radians a chaser craft should thrust along to meet a target craft, aiming at
+    /// where the target will be if the chaser closes in at `closing_speed` relative to it.
+    /// Falls back to aiming at the target's current position when it can't be caught
+    pub fn intercept_heading(
+        &self,
+        chaser: usize,
+        target: usize,
+        closing_speed: Real,
+    ) -> Result<Real, ApogeeError> {
+        let chaser = &self.crafts[self.craft_index(chaser)?];
+        let target = &self.crafts[self.craft_index(target)?];
+        let rel_pos = target.position - chaser.position;
+        let rel_vel = target.speed - chaser.speed;
+        let closing_speed = self.cfg.length_in(closing_speed);
+
+        // |rel_pos + rel_vel * t| = closing_speed * t
+        let a = rel_vel.magnitude_squared() - closing_speed * closing_speed;
+        let b = 2.0 * rel_pos.dot(&rel_vel);
+        let c = rel_pos.magnitude_squared();
+        let time = if a.abs() < Real::EPSILON {
+            -c / b
+        } else {
+            let discriminant = b * b - 4.0 * a * c;
+            if discriminant < 0.0 {
+                Real::NAN
+            } else {
+                let sqrt = discriminant.sqrt();
+                let (t1, t2) = ((-b - sqrt) / (2.0 * a), (-b + sqrt) / (2.0 * a));
+                let (lo, hi) = (t1.min(t2), t1.max(t2));
+                if lo > 0.0 {
+                    lo
+                } else {
+                    hi
+                }
+            }
+        };
+
+        let aim = if time > 0.0 {
+            rel_pos + rel_vel * time
+        } else {
+            rel_pos
+        };
+        Ok(math::atan2(aim.y, aim.x))
+    }
+
+    /// Makes a craft's prediction horizon span `periods` orbits around a planet instead of
+    /// [Config]'s fixed step count, and recomputes its trajectory
+    ///
+    /// If the craft isn't bound to the planet the period of a circular orbit at its current
+    /// distance is used instead
+    pub fn set_prediction_periods(
+        &mut self,
+        craft_index: usize,
+        periods: Real,
+        planet_index: usize,
+    ) -> Result<(), ApogeeError> {
+        if !(periods.is_finite() && periods > 0.0) {
+            return Err(ApogeeError::InvalidConfig(
+                "prediction periods must be positive",
+            ));
+        }
+        check_tick_time(self.cfg.tick_time)?;
+        let craft_index = self.craft_index(craft_index)?;
+        let planet = &self.planets[self.planet_index(planet_index)?];
+        let craft = &mut self.crafts[craft_index];
+        let period = craft.orbital_period(planet).unwrap_or_else(|| {
+            let distance = (craft.position - planet.position).magnitude();
+            consts::TAU * (math::powi(distance, 3) / planet.mu()).sqrt()
+        });
+        craft.prediction_steps = Some((periods * period / self.cfg.tick_time).ceil() as u64);
+        craft.clear_trajectory();
+        craft.reserve_trajectory(&self.cfg);
+        let steps = craft.prediction_steps(&self.cfg);
+        craft.populate_trajectory(&mut self.ephemeris, &self.cfg, self.time, steps);
+        Ok(())
+    }
+
+    /// Predicted positions of a target craft relative to a chaser craft, packed as `[x, y]`
+    /// pairs in a `Float64Array`, one per tick over the longer of the two prediction horizons
+    ///
+    /// Both crafts are propagated coasting from their current states, not read from their
+    /// trajectories, so the pairs always line up in time
+    pub fn relative_trajectory(
+        &mut self,
+        chaser: usize,
+        target: usize,
+    ) -> Result<Vec<Real>, ApogeeError> {
+        let chaser = &self.crafts[self.craft_index(chaser)?];
+        let target = &self.crafts[self.craft_index(target)?];
+        let steps = chaser
+            .prediction_steps(&self.cfg)
+            .max(target.prediction_steps(&self.cfg));
+        let start = |craft: &Craft| VelPos {
+            vel: craft.speed,
+            pos: craft.position,
+            time: self.time,
+            heading: craft.heading,
+        };
+        let (mut a, mut b) = (start(chaser), start(target));
+        let (drag_a, drag_b) = (chaser.drag_factor(), target.drag_factor());
+        let mut points = Vec::with_capacity(steps as usize * 2);
+        for _ in 0..steps {
+            let planets = self.ephemeris.at(&self.cfg, a.time);
+            a = coast_step(planets, &chaser.ignored_planets, &self.cfg, drag_a, a);
+            b = coast_step(planets, &target.ignored_planets, &self.cfg, drag_b, b);
+            let offset = self.cfg.vector_out(b.pos - a.pos);
+            points.extend([offset.x, offset.y]);
+        }
+        Ok(points)
+    }
+
+    /// Summarizes how a craft's predicted trajectory ends, reporting the earliest of an impact
+    /// or an escape within the prediction horizon
+    ///
+    /// A craft escapes once its energy exceeds the combined gravitational potential of every
+    /// planet, so a flyby of a moon doesn't count as leaving
+    pub fn orbit_status(&mut self, craft_index: usize) -> Result<OrbitStatus, ApogeeError> {
+        let craft_index = self.craft_index(craft_index)?;
+        let craft = &mut self.crafts[craft_index];
+        if craft.destroyed {
+            return Ok(OrbitStatus {
+                kind: OrbitStatusKind::Destroyed,
+                time: self.time,
+            });
+        }
+        let steps = craft.prediction_steps(&self.cfg);
+        craft.populate_trajectory(&mut self.ephemeris, &self.cfg, self.time, steps);
+        for vp in &craft.trajectory {
+            let planets = self.ephemeris.at(&self.cfg, vp.time);
+            let felt = felt_planets(planets, &craft.ignored_planets);
+            let kind = if felt.clone().any(|p| p.altitude_of(vp.pos) < 0.0) {
+                OrbitStatusKind::WillImpact
+            } else {
+                let potential: Real = felt
+                    .clone()
+                    .map(|p| -p.mu() / (vp.pos - p.position).magnitude())
+                    .sum();
+                // Measured in the frame the planets drift along with in n-body mode
+                let (mass, momentum) = felt.fold((0.0, DVec2::zeros()), |(m, momentum), p| {
+                    (m + p.mass, momentum + p.velocity * p.mass)
+                });
+                let drift = if mass > 0.0 {
+                    momentum / mass
+                } else {
+                    DVec2::zeros()
+                };
+                if (vp.vel - drift).magnitude_squared() / 2.0 + potential >= 0.0 {
+                    OrbitStatusKind::WillEscape
+                } else {
+                    continue;
+                }
+            };
+            return Ok(OrbitStatus {
+                kind,
+                time: vp.time,
+            });
+        }
+        Ok(OrbitStatus {
+            kind: OrbitStatusKind::Stable,
+            time: Time::NAN,
+        })
+    }
+
+    /// When a craft's prediction enters or leaves each planet's sphere of influence, in order.
+    /// The heaviest planet is the parent of all others, see [Planet::soi_radius]
+    ///
+    /// Crossings are interpolated linearly between trajectory points, planets the craft ignores
+    /// are skipped
+    pub fn soi_events(&mut self, craft_index: usize) -> Result<Vec<SoiEvent>, ApogeeError> {
+        let craft_index = self.craft_index(craft_index)?;
+        let craft = &mut self.crafts[craft_index];
+        if !craft.destroyed {
+            let steps = craft.prediction_steps(&self.cfg);
+            craft.populate_trajectory(&mut self.ephemeris, &self.cfg, self.time, steps);
+        }
+        let craft = &self.crafts[craft_index];
+        let start = VelPos {
+            pos: craft.position,
+            vel: craft.speed,
+            time: self.time,
+            heading: craft.heading,
+        };
+        let mut events = Vec::new();
+        // Signed distances to the sphere boundaries at the previous point
+        let mut prev: Option<(Time, Vec<Real>)> = None;
+        let mut frame = None;
+        let mut radii = Vec::new();
+        for vp in std::iter::once(&start).chain(&craft.trajectory) {
+            let planets = self.ephemeris.at(&self.cfg, vp.time);
+            // Points between ticks share their planet frame and so the radii
+            let tick = ((vp.time - self.time) / self.cfg.tick_time as Time).round();
+            if frame != Some(tick) {
+                frame = Some(tick);
+                radii = soi_radii(planets);
+            }
+            let offsets: Vec<Real> = planets
+                .iter()
+                .zip(&radii)
+                .map(|(p, r)| (vp.pos - p.position).magnitude() - r)
+                .collect();
+            if let Some((time, prev_offsets)) = &prev {
+                for (i, (&a, &b)) in prev_offsets.iter().zip(&offsets).enumerate() {
+                    if craft.ignores(i) || (a < 0.0) == (b < 0.0) {
+                        continue;
+                    }
+                    events.push(SoiEvent {
+                        planet: i,
+                        time: time + (vp.time - time) * a as Time / (a - b) as Time,
+                        is_entry: b < 0.0,
+                    });
+                }
+            }
+            prev = Some((vp.time, offsets));
+        }
+        Ok(events)
+    }
+
+    /// [Craft::conic_render_params] around a planet, also `None` when the other planets the craft
+    /// doesn't ignore pull on it too strongly
+    pub fn conic_render_params(
+        &self,
+        craft_index: usize,
+        planet_index: usize,
+    ) -> Result<Option<ConicParams>, ApogeeError> {
+        let craft = &self.crafts[self.craft_index(craft_index)?];
+        let planet_index = self.planet_index(planet_index)?;
+        let perturbation = craft.accel_vector()
+            + drag_accel(
+                &self.planets,
+                &craft.ignored_planets,
+                craft.drag_factor(),
+                craft.position,
+                craft.speed,
+            )
+            + self
+                .planets
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| i != planet_index && !craft.ignores(i))
+                .map(|(_, p)| p.gravity_accel_on(craft.position))
+                .fold(DVec2::zeros(), |a, b| a + b);
+        let cfg = &self.cfg;
+        Ok(craft
+            .conic_params_perturbed(&self.planets[planet_index], perturbation)
+            .map(|conic| ConicParams {
+                center: cfg.vector_out(conic.center.into()),
+                semi_major_axis: cfg.length_out(conic.semi_major_axis),
+                semi_minor_axis: cfg.length_out(conic.semi_minor_axis),
+                ..conic
+            }))
+    }
+
+    /// Closest approach of every craft to the center of a planet along its current state and
+    /// predicted trajectory, packed as `[distance, time]` pairs in craft order in a
+    /// `Float64Array`
+    pub fn all_closest_approaches(
+        &mut self,
+        planet_index: usize,
+    ) -> Result<Vec<Real>, ApogeeError> {
+        let planet_index = self.planet_index(planet_index)?;
+        let (ephemeris, cfg, time) = (&mut self.ephemeris, &self.cfg, self.time);
+        Ok(self
+            .crafts
+            .iter_mut()
+            .flat_map(|craft| {
+                if !craft.destroyed {
+                    let steps = craft.prediction_steps(cfg);
+                    craft.populate_trajectory(ephemeris, cfg, time, steps);
+                }
+                let (distance, time) = craft.closest_approach(ephemeris, cfg, time, planet_index);
+                [cfg.length_out(distance), time as Real]
+            })
+            .collect())
+    }
+
+    /// Signed angle from craft `a` to craft `b` as seen from a planet's center, counterclockwise
+    /// within `[-π, π)`
+    pub fn phase_angle(
+        &self,
+        planet_index: usize,
+        a: usize,
+        b: usize,
+    ) -> Result<Real, ApogeeError> {
+        let center = self.planets[self.planet_index(planet_index)?].position;
+        let a = self.crafts[self.craft_index(a)?].position - center;
+        let b = self.crafts[self.craft_index(b)?].position - center;
+        Ok(normalize_angle(math::atan2(a.perp(&b), a.dot(&b))))
+    }
+
+    /// Planet whose sphere of influence a craft is in, see [Craft::current_soi]
+    pub fn current_soi(&self, craft_index: usize) -> Result<Option<usize>, ApogeeError> {
+        let craft = &self.crafts[self.craft_index(craft_index)?];
+        Ok(craft.current_soi(&self.planets))
+    }
+
+    /// How many orbits around a planet a craft's prediction horizon spans
+    ///
+    /// Returns `0.0` if the craft isn't bound to the planet
+    pub fn orbits_predicted(
+        &self,
+        craft_index: usize,
+        planet_index: usize,
+    ) -> Result<Real, ApogeeError> {
+        let craft = &self.crafts[self.craft_index(craft_index)?];
+        let horizon = craft.prediction_steps(&self.cfg) as Real * self.cfg.tick_time;
+        let planet = &self.planets[self.planet_index(planet_index)?];
+        Ok(craft
+            .orbital_period(planet)
+            .map_or(0.0, |period| horizon / period))
+    }
+
+    /// Sets the seed of the generator used by the noise-injecting methods and restarts it
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.reseed();
+    }
+
+    /// Restarts the noise generator from the current seed, so a scenario can be re-run with
+    /// bit-identical results
+    pub fn reseed(&mut self) {
+        self.rng = Rng::new(self.seed);
+    }
+
+    /// Propagates `n_samples` copies of a craft with Gaussian noise added to its position and
+    /// velocity, returning each copy's closest approach to the center of a planet over the
+    /// prediction horizon as a `Float64Array`
+    ///
+    /// The noise is drawn from the simulation's seeded generator, see [Simulation::set_seed]. If
+    /// cancelled only the samples completed so far are returned
+    pub fn ensemble_closest_approach(
+        &mut self,
+        craft_index: usize,
+        planet_index: usize,
+        n_samples: u32,
+        pos_sigma: Real,
+        vel_sigma: Real,
+    ) -> Result<Vec<Real>, ApogeeError> {
+        let craft = &self.crafts[self.craft_index(craft_index)?];
+        let planet_index = self.planet_index(planet_index)?;
+        let (ephemeris, cfg, time) = (&mut self.ephemeris, &self.cfg, self.time);
+        let (pos_sigma, vel_sigma) = (cfg.length_in(pos_sigma), cfg.length_in(vel_sigma));
+        let drag = craft.drag_factor();
+        let steps = craft.prediction_steps(cfg);
+        let rng = &mut self.rng;
+        let cancel = &self.cancel;
+        cancel.reset();
+        Ok((0..n_samples)
+            .take_while(|_| !cancel.cancelled())
+            .map(|_| {
+                let offset =
+                    |rng: &mut Rng, sigma| DVec2::new(rng.gaussian(sigma), rng.gaussian(sigma));
+                let start = VelPos {
+                    pos: craft.position + offset(rng, pos_sigma),
+                    vel: craft.speed + offset(rng, vel_sigma),
+                    time,
+                    heading: craft.heading,
+                };
+                let distance = |ephemeris: &mut Ephemeris, state: &VelPos| {
+                    (state.pos - ephemeris.at(cfg, state.time)[planet_index].position).magnitude()
+                };
+                let mut state = start;
+                let mut closest = distance(ephemeris, &state);
+                for _ in 0..steps {
+                    let planets = ephemeris.at(cfg, state.time);
+                    state = coast_step(planets, &craft.ignored_planets, cfg, drag, state);
+                    closest = closest.min(distance(ephemeris, &state));
+                }
+                cfg.length_out(closest)
+            })
+            .collect())
+    }
+
+    /// Estimates how chaotic a craft's coasting trajectory is, as the mean exponential rate at
+    /// which a shadow copy offset by `delta` diverges from it over `horizon` seconds
+    ///
+    /// The separation is renormalized back to `delta` every tick so it never saturates. Values
+    /// much larger than `1 / horizon` mean the prediction becomes unreliable before its end. If
+    /// cancelled the rate is measured over the time propagated so far. Fails unless both the
+    /// tick time and `horizon` are positive
+    pub fn divergence(
+        &mut self,
+        craft_index: usize,
+        delta: Real,
+        horizon: Real,
+    ) -> Result<Real, ApogeeError> {
+        let craft = &self.crafts[self.craft_index(craft_index)?];
+        check_tick_time(self.cfg.tick_time)?;
+        if !(horizon.is_finite() && horizon > 0.0) {
+            return Err(ApogeeError::InvalidConfig("horizon must be positive"));
+        }
+        self.cancel.reset();
+        let delta = self.cfg.length_in(delta);
+        let drag = craft.drag_factor();
+        let steps = (horizon / self.cfg.tick_time).ceil().max(1.0) as u64;
+        let mut state = VelPos {
+            vel: craft.speed,
+            pos: craft.position,
+            time: self.time,
+            heading: craft.heading,
+        };
+        let mut shadow = VelPos {
+            pos: state.pos + DVec2::new(delta, 0.0),
+            ..state
+        };
+        let mut log_growth = 0.0;
+        let mut done = 0;
+        while done < steps && !self.cancel.cancelled() {
+            done += 1;
+            let (planets, ignored) = (
+                self.ephemeris.at(&self.cfg, state.time),
+                &craft.ignored_planets,
+            );
+            state = coast_step(planets, ignored, &self.cfg, drag, state);
+            shadow = coast_step(planets, ignored, &self.cfg, drag, shadow);
+            let separation = (shadow.pos - state.pos).magnitude();
+            if separation == 0.0 {
+                continue;
+            }
+            log_growth += math::ln(separation / delta);
+            let scale = delta / separation;
+            shadow.pos = state.pos + (shadow.pos - state.pos) * scale;
+            shadow.vel = state.vel + (shadow.vel - state.vel) * scale;
+        }
+        if done == 0 {
+            return Ok(0.0);
+        }
+        Ok(log_growth / (done as Real * self.cfg.tick_time))
+    }
+
+    /// Recomputes every craft's trajectory, calling the provided JS closure every `interval`
+    /// predicted steps with the fraction of the work completed so far
+    ///
+    /// If the closure throws the computation is stopped and the error is returned, if it cancels
+    /// the simulation's [CancelToken] the computation stops without an error. Either way the
+    /// trajectories keep the points computed so far and are extended by later ticks
+    pub fn recompute_trajectories_with_progress(
+        &mut self,
+        f: &js_sys::Function,
+        interval: u32,
+    ) -> Result<(), JsValue> {
+        self.cancel.reset();
+        let cancel = &self.cancel;
+        let this = JsValue::null();
+        let interval = u64::from(interval.max(1));
+        let cfg = &self.cfg;
+        let total = self
+            .crafts
+            .iter()
+            .map(|craft| craft.prediction_steps(cfg))
+            .sum::<u64>()
+            .max(1);
+        let mut done = 0;
+        for craft in &mut self.crafts {
+            craft.clear_trajectory();
+            let steps = craft.prediction_steps(&self.cfg);
+            let result = craft.populate_trajectory_with(
+                &mut self.ephemeris,
+                &self.cfg,
+                self.time,
+                steps,
+                || {
+                    done += 1;
+                    if done % interval == 0 {
+                        f.call1(&this, &JsValue::from(done as Real / total as Real))
+                            .map_err(Some)?;
+                    }
+                    // No error means the computation was cancelled
+                    if cancel.cancelled() {
+                        return Err(None);
+                    }
+                    Ok(())
+                },
+            );
+            match result {
+                Ok(()) => {}
+                Err(None) => break,
+                Err(Some(err)) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Validates a craft index
+    fn craft_index(&self, index: usize) -> Result<usize, ApogeeError> {
+        check_index("craft", index, self.crafts.len())
+    }
+
+    /// A craft's trajectory extended past the prediction horizon to at least `len` points
+    fn extended_trajectory(
+        &mut self,
+        craft_index: usize,
+        len: usize,
+    ) -> Result<&VecDeque<VelPos>, ApogeeError> {
+        let craft_index = self.craft_index(craft_index)?;
+        // Each pass below needs to predict at least one more tick to terminate
+        check_tick_time(self.cfg.tick_time)?;
+        let time = self.time;
+        let craft = &mut self.crafts[craft_index];
+        while craft.trajectory.len() < len {
+            // From the last point rather than a tick count, which the rounding in its time tag can
+            // throw off by a tick with single precision
+            let from = craft.trajectory.back().map_or(time, |vp| vp.time);
+            craft.populate_trajectory(&mut self.ephemeris, &self.cfg, from, 1);
+        }
+        Ok(&craft.trajectory)
+    }
+
+    /// Validates a planet index
+    fn planet_index(&self, index: usize) -> Result<usize, ApogeeError> {
+        check_index("planet", index, self.planets.len())
+    }
+
+    /// Average of a planet quantity weighted by mass, zero without planets
+    fn mass_weighted(&self, f: impl Fn(&Planet) -> DVec2) -> DVec2 {
+        let total: Real = self.planets.iter().map(|p| p.mass).sum();
+        if total == 0.0 {
+            return DVec2::zeros();
+        }
+        self.planets
+            .iter()
+            .map(|p| f(p) * p.mass)
+            .fold(DVec2::zeros(), |a, b| a + b)
+            / total
+    }
+
+    /// One vector per craft, followed by one per planet if `include_planets`
+    fn packed_states<'a>(
+        &'a self,
+        include_planets: bool,
+        craft: impl Fn(&Craft) -> DVec2 + 'a,
+        planet: impl Fn(&Planet) -> DVec2 + 'a,
+    ) -> impl Iterator<Item = DVec2> + 'a {
+        let planets = if include_planets {
+            &self.planets[..]
+        } else {
+            &[]
+        };
+        self.crafts
+            .iter()
+            .map(craft)
+            .chain(planets.iter().map(planet))
+    }
+
+    fn edit_planet(
+        &mut self,
+        index: usize,
+        f: impl FnOnce(&mut Planet),
+    ) -> Result<(), ApogeeError> {
+        let index = self.planet_index(index)?;
+        f(&mut self.planets[index]);
+        self.planets_changed();
+        Ok(())
+    }
+
+    fn edit_craft(&mut self, index: usize, f: impl FnOnce(&mut Craft)) -> Result<(), ApogeeError> {
+        let index = self.craft_index(index)?;
+        let craft = &mut self.crafts[index];
+        f(craft);
+        craft.clear_trajectory();
+        let steps = craft.prediction_steps(&self.cfg);
+        craft.populate_trajectory(&mut self.ephemeris, &self.cfg, self.time, steps);
+        craft.populate_powered_trajectory(&mut self.ephemeris, &self.cfg, self.time, steps);
+        Ok(())
+    }
+
+    /// Whether a live craft is thrusting or inside an atmosphere, see [Simulation::tick_warp]
+    fn needs_physics_warp(&self) -> bool {
+        self.crafts.iter().filter(|c| !c.destroyed).any(|c| {
+            c.throttle > 0.0
+                || c.effective_throttle > 0.0
+                || atmosphere_density(&self.planets, &c.ignored_planets, c.position) > 0.0
+        })
+    }
+
+    /// Brings everything derived from the planets up to date after editing them
+    fn planets_changed(&mut self) {
+        self.stop_planets();
+        self.reset_ephemeris();
+        self.history.clear();
+        self.recompute_craft_trajectories();
+    }
+
+    /// Zeroes the planets' velocities outside [Config::n_body] mode, where they don't move,
+    /// returning whether any had one
+    fn stop_planets(&mut self) -> bool {
+        if self.cfg.n_body {
+            return false;
+        }
+        let moving = self.planets.iter().any(|p| p.velocity != DVec2::zeros());
+        for planet in &mut self.planets {
+            planet.velocity = DVec2::zeros();
+        }
+        moving
+    }
+
+    /// Drops the planet states computed so far, which changes to the planets or the tick time
+    /// invalidate
+    fn reset_ephemeris(&mut self) {
+        self.ephemeris = Ephemeris::new(&self.planets, self.time);
+    }
+
+    /// Points the crafts as their [Craft::heading_mode] asks, keeping the heading when the
+    /// reference direction is undefined
+    fn update_headings(&mut self) {
+        let headings: Vec<Option<Real>> = self
+            .crafts
+            .iter()
+            .map(|craft| {
+                if craft.destroyed {
+                    return None;
+                }
+                if craft.heading_mode != HeadingMode::TargetRelative {
+                    let state = VelPos {
+                        vel: craft.speed,
+                        pos: craft.position,
+                        time: self.time,
+                        heading: craft.heading,
+                    };
+                    return craft.orbital_heading(&self.planets, &state);
+                }
+                craft
+                    .heading_target
+                    .and_then(|i| self.crafts.get(i))
+                    .filter(|target| !target.destroyed)
+                    .and_then(|target| heading_along(target.position - craft.position))
+            })
+            .collect();
+        for (craft, heading) in self.crafts.iter_mut().zip(headings) {
+            if let Some(heading) = heading {
+                craft.set_heading(heading);
+            }
+        }
+    }
+
+    /// Pushes the current state to the undo history, dropping the oldest past the depth limit
+    fn save_undo_state(&mut self) {
+        if self.undo_depth == 0 {
+            return;
+        }
+        if self.history.len() == self.undo_depth {
+            self.history.pop_front();
+        }
+        self.history.push_back(TickState {
+            time: self.time,
+            tick_count: self.tick_count,
+            planets: self.planets.clone(),
+            crafts: self.crafts.iter_mut().map(|c| c.physical_clone()).collect(),
+        });
+    }
+
+    fn recompute_craft_trajectories(&mut self) {
+        for craft in &mut self.crafts {
+            craft.clear_trajectory();
+            let steps = craft.prediction_steps(&self.cfg);
+            craft.populate_trajectory(&mut self.ephemeris, &self.cfg, self.time, steps);
+            craft.populate_powered_trajectory(&mut self.ephemeris, &self.cfg, self.time, steps);
+        }
+    }
+}
+
+/// Exerts gravity on [Craft]s
+#[wasm_bindgen]
+#[derive(Debug, Default, Clone)]
+pub struct Planet {
+    pub mass: Real,
+    /// Equatorial radius, along the x axis
+    pub radius: Real,
+    /// How much shorter the polar radius is than the equatorial one, relative to the latter.
+    /// Zero for a sphere
+    pub flattening: Real,
+    position: DVec2,
+    /// Only moves the planet in [Config::n_body] mode, a [Simulation] zeroes it otherwise. Orbits,
+    /// drag, heating, dynamic pressure, headings and surface speeds are all measured relative to it
+    velocity: DVec2,
+    /// Spin in rad/s, counterclockwise when positive. Only carries along crafts on the surface,
+    /// see [Simulation::launch_from_surface]
+    pub rotation_rate: Real,
+    /// Atmospheric density at the surface in kg/m³, zero for airless bodies
+    pub atmosphere_density: Real,
+    /// Altitude over which the atmospheric density drops by a factor of e
+    pub scale_height: Real,
+    /// Altitude marking the start of reentry, see [Simulation::on_entry_interface]. Zero
+    /// disables it, as does having no atmosphere
+    pub atmosphere_interface: Real,
+}
+
+#[wasm_bindgen]
+impl Planet {
+    #[wasm_bindgen(constructor)]
+    pub fn new(mass: Real, radius: Real, pos: AbiDVec2) -> Self {
+        Self {
+            mass,
+            radius,
+            position: pos.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a planet with the mass that produces the given gravitational acceleration on its
+    /// surface
+    pub fn from_surface_gravity(surface_g: Real, radius: Real, pos: AbiDVec2) -> Self {
+        Self::new(surface_g * math::powi(radius, 2) / G, radius, pos)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn position(&self) -> AbiDVec2 {
+        self.position.into()
+    }
+
+    /// Use [Simulation::move_planet] for planets already in a simulation
+    #[wasm_bindgen(setter)]
+    pub fn set_position(&mut self, pos: AbiDVec2) {
+        self.position = pos.into();
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn velocity(&self) -> AbiDVec2 {
+        self.velocity.into()
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_velocity(&mut self, vel: AbiDVec2) {
+        self.velocity = vel.into();
+    }
+
+    /// Radius along the y axis
+    #[wasm_bindgen(getter)]
+    pub fn polar_radius(&self) -> Real {
+        self.radius * (1.0 - self.flattening)
+    }
+
+    /// Sets the flattening so the polar radius matches, given the current equatorial radius
+    #[wasm_bindgen(setter)]
+    pub fn set_polar_radius(&mut self, polar_radius: Real) {
+        self.flattening = 1.0 - polar_radius / self.radius;
+    }
+
+    /// Standard gravitational parameter `μ = G·M`, in m³/s²
+    #[wasm_bindgen(getter)]
+    pub fn mu(&self) -> Real {
+        G * self.mass
+    }
+
+    /// Laplace sphere of influence radius `a·(m/M)^(2/5)` while orbiting a heavier parent at
+    /// its current distance
+    pub fn soi_radius(&self, parent: &Planet) -> Real {
+        let distance = (self.position - parent.position).magnitude();
+        distance * math::powf(self.mass / parent.mass, 0.4)
+    }
+
+    /// Gravitational acceleration at the planet's equator
+    pub fn surface_gravity(&self) -> Real {
+        self.mu() / math::powi(self.radius, 2)
+    }
+
+    /// Height of a point above the surface directly below it
+    pub fn altitude(&self, pos: AbiDVec2) -> Real {
+        self.altitude_of(pos.into())
+    }
+}
+
+impl Planet {
+    /// Height of a point above the surface, measured radially from the planet's center
+    fn altitude_of(&self, pos: DVec2) -> Real {
+        let offset = pos - self.position;
+        let distance = offset.magnitude();
+        if self.flattening == 0.0 || distance == 0.0 {
+            return distance - self.radius;
+        }
+        // Distance to the ellipse along the same direction, r = ab / √((b cos θ)² + (a sin θ)²)
+        let (a, b) = (self.radius, self.polar_radius());
+        let (cos, sin) = (offset.x / distance, offset.y / distance);
+        distance - a * b / (math::powi(b * cos, 2) + math::powi(a * sin, 2)).sqrt()
+    }
+
+    /// Point of the surface radially below or above a point, along with the outward normal there
+    fn surface_below(&self, pos: DVec2) -> (DVec2, DVec2) {
+        let offset = pos - self.position;
+        let distance = offset.magnitude();
+        if distance == 0.0 {
+            return (self.position + DVec2::x() * self.radius, DVec2::x());
+        }
+        let surface = offset * (1.0 - self.altitude_of(pos) / distance);
+        // Gradient of (x/a)² + (y/b)²
+        let (a, b) = (self.radius, self.polar_radius());
+        let normal = DVec2::new(surface.x / (a * a), surface.y / (b * b)).normalize();
+        (self.position + surface, normal)
+    }
+
+    /// Velocity of a point spinning along with the planet, including the planet's own motion
+    fn surface_velocity(&self, pos: DVec2) -> DVec2 {
+        let offset = pos - self.position;
+        self.velocity + DVec2::new(-offset.y, offset.x) * self.rotation_rate
+    }
+
+    /// Whether a point is above the entry interface, always true without one
+    fn above_interface(&self, pos: DVec2) -> bool {
+        self.atmosphere_density == 0.0
+            || self.atmosphere_interface <= 0.0
+            || self.altitude_of(pos) >= self.atmosphere_interface
+    }
+
+    /// Density of the exponential atmosphere at the given point
+    fn density_at(&self, pos: DVec2) -> Real {
+        if self.atmosphere_density == 0.0 || self.scale_height <= 0.0 {
+            return 0.0;
+        }
+        let altitude = self.altitude_of(pos).max(0.0);
+        self.atmosphere_density * math::exp(-altitude / self.scale_height)
+    }
+
+    /// Computes the gravitational acceleration applied on an object of negligible mass
+    fn gravity_accel_on(&self, pos: DVec2) -> DVec2 {
+        let mut dist = self.position - pos;
+        let accel_mod = self.mass * G / math::powi(dist.magnitude(), 2);
+        dist.set_magnitude(accel_mod);
+        dist
+    }
+
+    /// First-order post-Newtonian correction to the gravity on an object of negligible mass,
+    /// `3μh² / (c²r⁴)` toward the planet where `h` is the specific angular momentum
+    fn relativistic_accel_on(&self, pos: DVec2, vel: DVec2) -> DVec2 {
+        let mut dist = self.position - pos;
+        let r_sq = dist.magnitude_squared();
+        let h = dist.perp(&vel);
+        dist.set_magnitude(3.0 * self.mu() * h * h / (math::powi(SPEED_OF_LIGHT, 2) * r_sq * r_sq));
+        dist
+    }
+}
+
+/// Intersection point of the segments `a0-a1` and `b0-b1`, if they cross
+fn segment_intersection(a0: DVec2, a1: DVec2, b0: DVec2, b1: DVec2) -> Option<DVec2> {
+    let da = a1 - a0;
+    let db = b1 - b0;
+    let denom = da.perp(&db);
+    if denom == 0.0 {
+        // Parallel or degenerate segments
+        return None;
+    }
+    let offset = b0 - a0;
+    let t = offset.perp(&db) / denom;
+    let u = offset.perp(&da) / denom;
+    if (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u) {
+        Some(a0 + da * t)
+    } else {
+        None
+    }
+}
+
+/// Returns `index` if it's within `0..len`
+fn check_index(kind: &'static str, index: usize, len: usize) -> Result<usize, ApogeeError> {
+    if index < len {
+        Ok(index)
+    } else {
+        Err(ApogeeError::IndexOutOfBounds { kind, index, len })
+    }
+}
+
+/// Fails unless the tick time is a positive, finite duration
+fn check_tick_time(tick_time: Real) -> Result<(), ApogeeError> {
+    if tick_time > 0.0 && tick_time.is_finite() {
+        Ok(())
+    } else {
+        Err(ApogeeError::InvalidConfig("tick time must be positive"))
+    }
+}
+
+/// Values per planet in [Simulation::add_planets_packed]
+const PLANET_GROUP_LEN: usize = 4;
+/// Values per craft in [Simulation::add_crafts_packed]
+const CRAFT_GROUP_LEN: usize = 9;
+
+/// Splits packed input into groups of `len` values, failing if it doesn't divide evenly
+fn packed_groups(
+    data: &[Real],
+    len: usize,
+) -> Result<std::slice::ChunksExact<'_, Real>, ApogeeError> {
+    if !data.len().is_multiple_of(len) {
+        return Err(ApogeeError::InvalidPackedLength {
+            len: data.len(),
+            group: len,
+        });
+    }
+    Ok(data.chunks_exact(len))
+}
+
+/// The planets whose entries in an `ignored` mask aren't set, see [Craft::set_planet_ignored].
+/// Planets past the end of the mask aren't ignored
+fn felt_planets<'a>(
+    planets: &'a [Planet],
+    ignored: &'a [bool],
+) -> impl Iterator<Item = &'a Planet> + Clone + 'a {
+    planets
+        .iter()
+        .enumerate()
+        .filter(move |&(i, _)| !ignored.get(i).copied().unwrap_or(false))
+        .map(|(_, p)| p)
+}
+
+/// Sums the gravitational acceleration of every planet not `ignored` on an object at the given
+/// state
+fn gravity_accel(
+    planets: &[Planet],
+    ignored: &[bool],
+    cfg: &Config,
+    pos: DVec2,
+    vel: DVec2,
+) -> DVec2 {
+    #[cfg(feature = "simd")]
+    let newtonian = simd::gravity_accel(felt_planets(planets, ignored), pos);
+    #[cfg(not(feature = "simd"))]
+    let newtonian = felt_planets(planets, ignored)
+        .map(|p| p.gravity_accel_on(pos))
+        .fold(DVec2::zeros(), |a, b| a + b);
+    if !cfg.relativistic {
+        return newtonian;
+    }
+    felt_planets(planets, ignored)
+        .map(|p| p.relativistic_accel_on(pos, vel))
+        .fold(newtonian, |a, b| a + b)
+}
+
+/// Advances planets attracting each other by one tick with a semi-implicit Euler step
+fn step_planets(planets: &mut [Planet], cfg: &Config) {
+    let accels: Vec<DVec2> = planets
+        .iter()
+        .enumerate()
+        .map(|(i, planet)| {
+            planets
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(_, other)| other.gravity_accel_on(planet.position))
+                .fold(DVec2::zeros(), |a, b| a + b)
+        })
+        .collect();
+    for (planet, accel) in planets.iter_mut().zip(accels) {
+        planet.velocity += accel * cfg.tick_time;
+        planet.position += planet.velocity * cfg.tick_time;
+    }
+}
+
+/// Sphere of influence radii of the planets taking the heaviest one as everyone's parent, which
+/// then has an infinite one
+fn soi_radii(planets: &[Planet]) -> Vec<Real> {
+    let primary = primary_planet(planets);
+    planets
+        .iter()
+        .enumerate()
+        .map(|(i, p)| match primary {
+            Some(primary) if primary != i => p.soi_radius(&planets[primary]),
+            _ => Real::INFINITY,
+        })
+        .collect()
+}
+
+/// Index of the heaviest planet, the parent of all others for spheres of influence
+fn primary_planet(planets: &[Planet]) -> Option<usize> {
+    planets
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.mass.total_cmp(&b.mass))
+        .map(|(i, _)| i)
+}
+
+/// Index of the planet not `ignored` exerting the strongest pull at the given point
+fn dominant_planet(planets: &[Planet], ignored: &[bool], pos: DVec2) -> Option<usize> {
+    planets
+        .iter()
+        .map(|p| p.mass / (p.position - pos).magnitude_squared())
+        .enumerate()
+        .filter(|&(i, _)| !ignored.get(i).copied().unwrap_or(false))
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(i, _)| i)
+}
+
+/// Finds an apsis between two consecutive states, where the radial velocity relative to the
+/// planet changes sign
+fn apsis_between(planet: &Planet, a: &VelPos, b: &VelPos) -> Option<Apsis> {
+    let radial_a = (a.pos - planet.position).dot(&(a.vel - planet.velocity));
+    let radial_b = (b.pos - planet.position).dot(&(b.vel - planet.velocity));
+    if radial_a == 0.0 || radial_a.signum() == radial_b.signum() {
+        return None;
+    }
+    let fraction = radial_a / (radial_a - radial_b);
+    Some(Apsis {
+        time: a.time + (b.time - a.time) * fraction as Time,
+        pos: a.pos + (b.pos - a.pos) * fraction,
+    })
+}
+
+/// Combined atmospheric density of every planet not `ignored` at the given point
+fn atmosphere_density(planets: &[Planet], ignored: &[bool], pos: DVec2) -> Real {
+    felt_planets(planets, ignored)
+        .map(|p| p.density_at(pos))
+        .sum()
+}
+
+/// Acceleration from atmospheric drag, `drag` being the craft's [Craft::drag_factor]. Each
+/// planet's air moves along with it
+fn drag_accel(planets: &[Planet], ignored: &[bool], drag: Real, pos: DVec2, vel: DVec2) -> DVec2 {
+    if drag == 0.0 {
+        return DVec2::zeros();
+    }
+    felt_planets(planets, ignored)
+        .map(|p| {
+            let density = p.density_at(pos);
+            if density == 0.0 {
+                return DVec2::zeros();
+            }
+            let airspeed = vel - p.velocity;
+            -airspeed * (drag * density * airspeed.magnitude())
+        })
+        .fold(DVec2::zeros(), |a, b| a + b)
+}
+
+/// Acceleration on an unpowered craft, from gravity and atmospheric drag
+fn coast_accel(
+    planets: &[Planet],
+    ignored: &[bool],
+    cfg: &Config,
+    drag: Real,
+    state: &VelPos,
+) -> DVec2 {
+    gravity_accel(planets, ignored, cfg, state.pos, state.vel)
+        + drag_accel(planets, ignored, drag, state.pos, state.vel)
+}
+
+/// Advances a coasting craft by one tick, see [tick_step]
+fn coast_step(
+    planets: &[Planet],
+    ignored: &[bool],
+    cfg: &Config,
+    drag: Real,
+    state: VelPos,
+) -> VelPos {
+    tick_step(planets, ignored, cfg, drag, state, DVec2::zeros())
+}
+
+/// Advances a craft by one tick under gravity, drag and a constant `thrust` acceleration using
+/// semi-implicit Euler, in shorter substeps wherever the acceleration is high. Planets `ignored`
+/// don't act on it
+fn tick_step(
+    planets: &[Planet],
+    ignored: &[bool],
+    cfg: &Config,
+    drag: Real,
+    state: VelPos,
+    thrust: DVec2,
+) -> VelPos {
+    let max_substeps = cfg.max_substeps.max(1);
+    let end = state.time + cfg.tick_time as Time;
+    let mut next = state;
+    // Re-evaluated every substep since a tick can start far from the periapsis and cross it.
+    // Each is at least a `max_substeps`-th of the tick, so that many end it even when the tick
+    // time is NaN and nothing compares equal
+    for substep in 1..=max_substeps {
+        let accel = coast_accel(planets, ignored, cfg, drag, &next) + thrust;
+        let substeps = if cfg.substep_accel > 0.0 {
+            let ratio = accel.magnitude() / cfg.substep_accel;
+            (ratio.ceil() as u32).clamp(1, max_substeps)
+        } else {
+            1
+        };
+        let remaining = (end - next.time) as Real;
+        let dt = cfg.tick_time / substeps as Real;
+        // Snap to the tick grid instead of leaving a sliver of a substep
+        let dt = if substep == max_substeps || dt >= remaining * (1.0 - 1e-9) {
+            remaining
+        } else {
+            dt
+        };
+        next.vel += accel * dt;
+        next.pos += next.vel * dt;
+        next.time += dt as Time;
+        if dt == remaining {
+            break;
+        }
+    }
+    next.time = end;
+    next
+}
+
+/// Number of points a tick starting from `state` is drawn with so the velocity turns by at most
+/// [ADAPTIVE_MAX_TURN] between them
+fn adaptive_substeps(planets: &[Planet], ignored: &[bool], cfg: &Config, state: &VelPos) -> u32 {
+    let speed_sq = state.vel.magnitude_squared();
+    if speed_sq == 0.0 {
+        return 1;
+    }
+    // Only the acceleration normal to the velocity bends the path, at a rate of a_n / v
+    let accel = gravity_accel(planets, ignored, cfg, state.pos, state.vel);
+    let turn = accel.perp(&state.vel).abs() / speed_sq * cfg.tick_time;
+    ((turn / ADAPTIVE_MAX_TURN).ceil() as u32).clamp(1, ADAPTIVE_MAX_SUBSTEPS)
+}
+
+/// Angle of a direction within `[-π, π)`, `None` for the zero vector
+fn heading_along(direction: DVec2) -> Option<Real> {
+    (direction.magnitude_squared() > 0.0)
+        .then(|| normalize_angle(math::atan2(direction.y, direction.x)))
+}
+
+/// Wraps an angle in radians into `[-π, π)`
+fn normalize_angle(angle: Real) -> Real {
+    let wrapped = (angle + consts::PI).rem_euclid(consts::TAU) - consts::PI;
+    // rem_euclid can round up to exactly τ for tiny negative inputs
+    if wrapped >= consts::PI {
+        wrapped - consts::TAU
+    } else {
+        wrapped
+    }
+}
+
+/// Cubic Hermite interpolation between two states at fraction `s` of the way from `a` to `b`
+///
+/// Only used to add display points, the integration itself always steps by whole ticks
+fn hermite(a: &VelPos, b: &VelPos, s: Real) -> VelPos {
+    let dt = (b.time - a.time) as Real;
+    let (s2, s3) = (s * s, s * s * s);
+    let pos = a.pos * (2.0 * s3 - 3.0 * s2 + 1.0)
+        + a.vel * (dt * (s3 - 2.0 * s2 + s))
+        + b.pos * (3.0 * s2 - 2.0 * s3)
+        + b.vel * (dt * (s3 - s2));
+    let vel = (a.pos - b.pos) * ((6.0 * s2 - 6.0 * s) / dt)
+        + a.vel * (3.0 * s2 - 4.0 * s + 1.0)
+        + b.vel * (3.0 * s2 - 2.0 * s);
+    VelPos {
+        vel,
+        pos,
+        time: a.time + (dt * s) as Time,
+        heading: a.heading,
+    }
+}
+
+// How do I pass this stuff by value to JS
+#[wasm_bindgen]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AbiDVec2 {
+    pub x: Real,
+    pub y: Real,
+}
+
+impl From<DVec2> for AbiDVec2 {
+    fn from(vec: DVec2) -> Self {
+        Self { x: vec.x, y: vec.y }
+    }
+}
+
+impl From<AbiDVec2> for DVec2 {
+    fn from(vec: AbiDVec2) -> Self {
+        DVec2::new(vec.x, vec.y)
+    }
+}
+
+/// Axis-aligned rectangle, used to frame a trajectory
+#[wasm_bindgen]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AbiRect {
+    pub min_x: Real,
+    pub min_y: Real,
+    pub max_x: Real,
+    pub max_y: Real,
+}
+
+/// Circle enclosing a set of points
+#[wasm_bindgen]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AbiCircle {
+    pub center: AbiDVec2,
+    pub radius: Real,
+}
+
+/// What happens to a craft over its prediction horizon, see [Simulation::orbit_status]
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrbitStatusKind {
+    Stable,
+    WillImpact,
+    WillEscape,
+    /// Already destroyed, the time is the current one
+    Destroyed,
+}
+
+/// Attitude a craft holds on its own, set every [Simulation::tick] before thrusting. The
+/// orbital directions are relative to the planet pulling the craft the strongest
+#[wasm_bindgen]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum HeadingMode {
+    /// Keeps whatever [Craft::heading] was set to in the inertial frame
+    #[default]
+    Fixed,
+    /// Along the velocity relative to the planet
+    Prograde,
+    /// Against the velocity relative to the planet
+    Retrograde,
+    /// Towards the planet's center
+    RadialIn,
+    /// Away from the planet's center
+    RadialOut,
+    /// Towards the [Craft::heading_target] craft
+    TargetRelative,
+}
+
+/// What happens to a craft hitting a planet's surface during a tick
+#[wasm_bindgen]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionMode {
+    /// Lets the craft fly through the planet as if it weren't solid, the default since crafts
+    /// didn't collide with anything before collision modes existed
+    #[default]
+    PassThrough,
+    /// Destroys the craft
+    Destroy,
+    /// Lands the craft where it hit, at rest relative to the planet
+    Stop,
+    /// Reflects the craft off the surface, keeping [Craft::restitution] of the speed into it
+    Bounce,
+}
+
+/// An [OrbitStatusKind] along with when it happens, `NaN` for stable orbits
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy)]
+pub struct OrbitStatus {
+    pub kind: OrbitStatusKind,
+    pub time: Time,
+}
+
+/// Shape and phase of a two-body orbit, see [Craft::orbital_elements]
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy)]
+pub struct OrbitalElements {
+    /// Negative for hyperbolic trajectories
+    pub semi_major_axis: Real,
+    pub eccentricity: Real,
+    /// Direction of the periapsis in radians counterclockwise from the x axis
+    pub argument_of_periapsis: Real,
+    /// Angle from the periapsis to the craft in the direction of motion, within `[-π, π)`
+    pub true_anomaly: Real,
+    /// `NaN` if unbound
+    pub period: Real,
+}
+
+/// Conic section a two-body orbit follows, for drawing it exactly, see
+/// [Craft::conic_render_params]
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy)]
+pub struct ConicParams {
+    /// Center of the ellipse, or the point between the branches of the hyperbola
+    pub center: AbiDVec2,
+    pub semi_major_axis: Real,
+    pub semi_minor_axis: Real,
+    /// Direction of the major axis in radians counterclockwise from the x axis, pointing from
+    /// the center towards the periapsis
+    pub rotation: Real,
+    /// The craft follows the branch around the planet
+    pub hyperbolic: bool,
+}
+
+/// Where and how fast a craft is predicted to hit a planet, see [Craft::predicted_impact]
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy)]
+pub struct Impact {
+    pub position: AbiDVec2,
+    /// Speed relative to the planet, not to its spinning surface
+    pub speed: Real,
+    pub time: Time,
+}
+
+/// A craft crossing a planet's sphere of influence, see [Simulation::soi_events]
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy)]
+pub struct SoiEvent {
+    pub planet: usize,
+    pub time: Time,
+    /// Whether the craft enters the sphere rather than leaving it
+    pub is_entry: bool,
+}
+
+/// A local extremum of the distance to the dominant planet along a trajectory
+#[derive(Debug, Clone, Copy)]
+struct Apsis {
+    time: Time,
+    pos: DVec2,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct VelPos {
+    pub vel: DVec2,
+    pub pos: DVec2,
+    /// Simulation time this state is predicted for
+    pub time: Time,
+    /// Direction the craft faces. Predictions steer it as [Craft::heading_mode] asks, except
+    /// towards a target, and keep it otherwise
+    pub heading: Real,
+}
+
+/// A predicted state of a craft
+#[wasm_bindgen]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AbiVelPos {
+    pub pos: AbiDVec2,
+    pub vel: AbiDVec2,
+    pub time: Time,
+    pub heading: Real,
+}
+
+impl From<VelPos> for AbiVelPos {
+    fn from(vp: VelPos) -> Self {
+        Self {
+            pos: vp.pos.into(),
+            vel: vp.vel.into(),
+            time: vp.time,
+            heading: vp.heading,
+        }
+    }
+}
+
+impl From<VelPos> for (DVec2, DVec2) {
+    fn from(value: VelPos) -> Self {
+        (value.vel, value.pos)
+    }
+}
+
+/// Represents a spacecraft propelled by a reaction motor
+#[wasm_bindgen]
+#[derive(Debug, Default, Clone)]
+pub struct Craft {
+    pub dry_mass: Real,
+    pub fuel_mass: Real,
+    pub isp: Real,
+    pub thrust: Real,
+    /// Gravity losses of an ascent as a fraction of its ideal delta-v, used by
+    /// [Craft::launch_deltav_estimate]
+    pub gravity_loss_factor: Real,
+    position: DVec2,
+    speed: DVec2,
+    /// Kept within `[-π, π)` by [Craft::set_heading]
+    heading: Real,
+    /// How [Simulation::tick] steers [Craft::heading] before thrusting
+    pub heading_mode: HeadingMode,
+    /// Index of the craft [HeadingMode::TargetRelative] points at
+    pub heading_target: Option<usize>,
+    /// Direction in radians the engine thrusts along instead of [Craft::heading], for crafts
+    /// whose attitude is controlled separately
+    pub thrust_direction: Option<Real>,
+    /// Commanded throttle within `[0, 1]`, kept there by [Craft::set_throttle]
+    throttle: Real,
+    /// How fast the engine follows [Craft::throttle] changes, in throttle units per second.
+    /// Zero makes it respond instantly
+    pub throttle_response_rate: Real,
+    /// Throttle the engine is actually running at
+    effective_throttle: Real,
+    /// Average acceleration over the last tick
+    last_acceleration: DVec2,
+    /// Non-gravitational part of the acceleration at the start of the last tick
+    applied_acceleration: DVec2,
+    /// Dynamic pressure at the end of the last tick and whether it was increasing
+    last_dynamic_pressure: Real,
+    dynamic_pressure_rising: bool,
+    /// Peak pressure of the latest Max-Q
+    max_q: Real,
+    pub drag_coefficient: Real,
+    /// Cross-sectional area facing the airflow, in m², also used for heating and cooling
+    pub drag_area: Real,
+    /// Temperature in kelvin above which the craft is destroyed, zero disables the limit
+    pub max_temperature: Real,
+    pub collision_mode: CollisionMode,
+    /// Fraction of the speed into the surface kept when bouncing off it, see
+    /// [CollisionMode::Bounce]
+    pub restitution: Real,
+    /// Temperature above [AMBIENT_TEMPERATURE], so crafts start at ambient by default
+    heat: Real,
+    destroyed: bool,
+    /// Overrides [Config]'s prediction step count, see [Simulation::set_prediction_periods]
+    prediction_steps: Option<u64>,
+    /// Inverse of [Craft::predict] so crafts predict by default
+    prediction_disabled: bool,
+    /// [Craft::fuel_mass] when [Craft::mark_fuel] was last called
+    fuel_mark: Option<Real>,
+    /// Shared so the copies kept for undoing ticks don't allocate
+    name: Option<Arc<str>>,
+    /// Planets by index that neither pull nor drag the craft, missing ones aren't ignored
+    ignored_planets: Vec<bool>,
+    /// Predicted states after the current time. Every tick leaves exactly
+    /// [Craft::prediction_steps] tick states in it whether the craft is coasting or thrusting,
+    /// plus any points interpolated between them by [Config::adaptive_sampling]
+    trajectory: VecDeque<VelPos>,
+    /// States predicted with the engine held at [Craft::throttle] until the fuel runs out, one
+    /// per tick over the same horizon. Empty while the engine is off, the prediction coasts then
+    powered_trajectory: VecDeque<VelPos>,
+    apsides: VecDeque<Apsis>,
+}
+
+#[wasm_bindgen]
+impl Craft {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn position(&self) -> AbiDVec2 {
+        self.position.into()
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_position(&mut self, pos: AbiDVec2) {
+        self.position = pos.into()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn speed(&self) -> AbiDVec2 {
+        self.speed.into()
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_speed(&mut self, vel: AbiDVec2) {
+        self.speed = vel.into()
+    }
+
+    /// Direction the craft faces in radians counterclockwise from the x axis, within `[-π, π)`
+    #[wasm_bindgen(getter)]
+    pub fn heading(&self) -> Real {
+        self.heading
+    }
+
+    /// Wraps any angle into `[-π, π)`
+    #[wasm_bindgen(setter)]
+    pub fn set_heading(&mut self, heading: Real) {
+        self.heading = normalize_angle(heading);
+    }
+
+    /// Magnitude of [Craft::speed]
+    pub fn speed_magnitude(&self) -> Real {
+        self.speed.magnitude()
+    }
+
+    /// Speed relative to a planet, which only differs from [Craft::speed_magnitude] for planets
+    /// moving in [Config::n_body] mode
+    pub fn relative_speed(&self, planet: &Planet) -> Real {
+        self.relative_velocity(planet).magnitude()
+    }
+
+    /// [Craft::heading] in degrees, normalized to `[0, 360)`
+    #[wasm_bindgen(getter)]
+    pub fn heading_degrees(&self) -> Real {
+        let degrees = self.heading.to_degrees().rem_euclid(360.0);
+        // rem_euclid can round up to exactly 360 for tiny negative headings
+        if degrees >= 360.0 {
+            degrees - 360.0
+        } else {
+            degrees
+        }
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_heading_degrees(&mut self, degrees: Real) {
+        self.set_heading(degrees.to_radians());
+    }
+
+    /// Computes the craft's delta-v with the real-world [STANDARD_GRAVITY], see
+    /// [Simulation::deltav] for the configured one
+    pub fn deltav(&self) -> Real {
+        self.deltav_with(STANDARD_GRAVITY)
+    }
+
+    /// Predicted state at an index of the trajectory buffer, `None` past its end
+    pub fn trajectory_state(&self, index: usize) -> Option<AbiVelPos> {
+        self.trajectory.get(index).copied().map(Into::into)
+    }
+
+    /// Whether the trajectory is kept predicted every tick, on by default. Turning it off saves
+    /// the work for crafts nobody looks at without changing how they move, their trajectory
+    /// stays empty unless requested through [Simulation::trajectory_chunk]
+    #[wasm_bindgen(getter)]
+    pub fn predict(&self) -> bool {
+        !self.prediction_disabled
+    }
+
+    /// Use [Simulation::set_craft_predict] for crafts already in a simulation
+    #[wasm_bindgen(setter)]
+    pub fn set_predict(&mut self, predict: bool) {
+        self.prediction_disabled = !predict;
+    }
+
+    /// Label to tell crafts apart in the UI, not used by the physics
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> Option<String> {
+        self.name.as_deref().map(String::from)
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_name(&mut self, name: Option<String>) {
+        self.name = name.map(Arc::from);
+    }
+
+    /// Makes the planet at an index pass through the craft without pulling, dragging or heating
+    /// it. Only applies to crafts not yet added to a simulation, see
+    /// [Simulation::set_planet_ignored]
+    pub fn set_planet_ignored(&mut self, planet_index: usize, ignored: bool) {
+        if self.ignored_planets.len() <= planet_index {
+            self.ignored_planets.resize(planet_index + 1, false);
+        }
+        self.ignored_planets[planet_index] = ignored;
+    }
+
+    /// Whether the craft ignores the planet at an index, see [Craft::set_planet_ignored]
+    pub fn ignores(&self, planet_index: usize) -> bool {
+        self.ignored_planets
+            .get(planet_index)
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// Number of points in the predicted trajectory, including any interpolated by
+    /// [Config::adaptive_sampling]
+    pub fn trajectory_len(&self) -> usize {
+        self.trajectory.len()
+    }
+
+    pub fn trajectory_is_empty(&self) -> bool {
+        self.trajectory.is_empty()
+    }
+
+    /// First predicted state, `None` if the trajectory is empty
+    pub fn trajectory_start(&self) -> Option<AbiVelPos> {
+        self.trajectory.front().copied().map(Into::into)
+    }
+
+    /// Last predicted state, where the prediction horizon ends. `None` if the trajectory is empty
+    pub fn trajectory_end(&self) -> Option<AbiVelPos> {
+        self.trajectory.back().copied().map(Into::into)
+    }
+
+    /// Iterates over the predicted states, the powered ones while the engine is on and the
+    /// coasting ones otherwise
+    // Not JS iterator compliant but should be good enough?
+    pub fn trajectory_iter(&self) -> TrajectoryIter {
+        let inner = if self.powered_trajectory.is_empty() {
+            &self.trajectory
+        } else {
+            &self.powered_trajectory
+        };
+        TrajectoryIter {
+            inner: inner as *const _,
+        }
+    }
+
+    /// Iterates over the coasting prediction even while thrusting, the ballistic path the craft
+    /// would follow if the engine cut now
+    pub fn coast_trajectory_iter(&self) -> TrajectoryIter {
+        TrajectoryIter {
+            inner: &self.trajectory as *const _,
+        }
+    }
+
+    /// State predicted with the engine on at an index, `None` past the end or while the engine
+    /// is off. The other `trajectory_` methods use the coasting prediction
+    pub fn powered_trajectory_state(&self, index: usize) -> Option<AbiVelPos> {
+        self.powered_trajectory.get(index).copied().map(Into::into)
+    }
+
+    /// Axis-aligned bounding box of the craft and its predicted trajectory
+    ///
+    /// Collapses to the craft's position if the trajectory is empty
+    pub fn trajectory_bounds(&self) -> AbiRect {
+        let (min, max) = self
+            .trajectory
+            .iter()
+            .fold((self.position, self.position), |(min, max), vp| {
+                (min.inf(&vp.pos), max.sup(&vp.pos))
+            });
+        AbiRect {
+            min_x: min.x,
+            min_y: min.y,
+            max_x: max.x,
+            max_y: max.y,
+        }
+    }
+
+    /// Circle centered on the trajectory's bounding box that encloses every point
+    ///
+    /// Has zero radius around the craft's position if the trajectory is empty
+    pub fn trajectory_bounding_circle(&self) -> AbiCircle {
+        let bounds = self.trajectory_bounds();
+        let center = DVec2::new(
+            (bounds.min_x + bounds.max_x) / 2.0,
+            (bounds.min_y + bounds.max_y) / 2.0,
+        );
+        let radius = self
+            .trajectory
+            .iter()
+            .map(|vp| (vp.pos - center).magnitude())
+            .fold((self.position - center).magnitude(), Real::max);
+        AbiCircle {
+            center: center.into(),
+            radius,
+        }
+    }
+
+    /// Net acceleration from gravity, thrust and drag averaged over the last tick, in m/s²
+    #[wasm_bindgen(getter)]
+    pub fn last_acceleration(&self) -> AbiDVec2 {
+        self.last_acceleration.into()
+    }
+
+    /// Load from thrust and drag during the last tick in multiples of [STANDARD_GRAVITY], zero
+    /// in free fall
+    pub fn g_force(&self) -> Real {
+        self.applied_acceleration.magnitude() / STANDARD_GRAVITY
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn throttle(&self) -> Real {
+        self.throttle
+    }
+
+    /// Clamps to `[0, 1]` so a stray value can't reverse or overdrive the engine, `NaN` shuts
+    /// it off
+    #[wasm_bindgen(setter)]
+    pub fn set_throttle(&mut self, throttle: Real) {
+        self.throttle = if throttle.is_nan() {
+            0.0
+        } else {
+            throttle.clamp(0.0, 1.0)
+        };
+    }
+
+    /// Remembers the current [Craft::fuel_mass] for [Craft::fuel_used_since_mark]
+    pub fn mark_fuel(&mut self) {
+        self.fuel_mark = Some(self.fuel_mass);
+    }
+
+    /// Fuel burned in kg since the last [Craft::mark_fuel], `NaN` if it was never called.
+    /// Negative if fuel was added in between
+    pub fn fuel_used_since_mark(&self) -> Real {
+        self.fuel_mark
+            .map_or(Real::NAN, |mark| mark - self.fuel_mass)
+    }
+
+    /// Whether the engine is running with fuel left, see [Craft::effective_throttle]
+    pub fn is_thrusting(&self) -> bool {
+        !self.destroyed && self.effective_throttle > 0.0 && self.fuel_mass > 0.0
+    }
+
+    /// Acceleration the engine currently gives the craft, in m/s² along the thrust direction
+    pub fn thrust_vector(&self) -> AbiDVec2 {
+        if self.destroyed {
+            return AbiDVec2::default();
+        }
+        self.accel_vector().into()
+    }
+
+    /// Throttle the engine is actually running at while it spools towards [Craft::throttle]
+    #[wasm_bindgen(getter)]
+    pub fn effective_throttle(&self) -> Real {
+        self.effective_throttle
+    }
+
+    /// Dynamic pressure at the latest peak [Simulation::on_max_q] reported, zero before the first
+    #[wasm_bindgen(getter)]
+    pub fn max_q(&self) -> Real {
+        self.max_q
+    }
+
+    /// Hull temperature in kelvin
+    #[wasm_bindgen(getter)]
+    pub fn temperature(&self) -> Real {
+        AMBIENT_TEMPERATURE + self.heat
+    }
+
+    /// Whether the craft overheated past its [Craft::max_temperature] or crashed with
+    /// [CollisionMode::Destroy], destroyed crafts are no longer simulated
+    #[wasm_bindgen(getter)]
+    pub fn destroyed(&self) -> bool {
+        self.destroyed
+    }
+
+    /// Specific orbital energy `v²/2 - μ/r` relative to a planet, in J/kg. Negative when the
+    /// craft is bound to it
+    pub fn specific_orbital_energy(&self, planet: &Planet) -> Real {
+        let r = (self.position - planet.position).magnitude();
+        self.relative_velocity(planet).magnitude_squared() / 2.0 - planet.mu() / r
+    }
+
+    /// Specific energy binding the craft to a planet, see [Craft::specific_orbital_energy].
+    /// Zero if the craft is already unbound
+    pub fn binding_energy(&self, planet: &Planet) -> Real {
+        self.specific_orbital_energy(planet).min(0.0)
+    }
+
+    /// Delta-v still needed to escape a planet when burning prograde from the current state,
+    /// zero if the craft is already unbound
+    pub fn escape_deltav(&self, planet: &Planet) -> Real {
+        let r = (self.position - planet.position).magnitude();
+        let escape_speed = (2.0 * planet.mu() / r).sqrt();
+        (escape_speed - self.relative_speed(planet)).max(0.0)
+    }
+
+    /// Hyperbolic excess speed `√(2ε)` the craft keeps once it has escaped a planet, `None`
+    /// if it's bound to it
+    pub fn v_infinity(&self, planet: &Planet) -> Option<Real> {
+        let energy = self.specific_orbital_energy(planet);
+        (energy > 0.0).then(|| (2.0 * energy).sqrt())
+    }
+
+    /// Delta-v of turning the velocity towards a direction while keeping the same speed,
+    /// `2v·sin(θ/2)` for a turn by θ. `NaN` if the direction is a zero vector
+    pub fn course_change_deltav(&self, new_velocity_direction: AbiDVec2) -> Real {
+        let direction = DVec2::from(new_velocity_direction);
+        match direction.try_normalize(0.0) {
+            Some(direction) => (direction * self.speed.magnitude() - self.speed).magnitude(),
+            None => Real::NAN,
+        }
+    }
+
+    /// Rough delta-v budget to reach a circular orbit at an altitude above a planet's equator,
+    /// for planning before launch rather than simulating the ascent
+    ///
+    /// The ideal part is the energy of climbing from the surface at rest plus the orbital
+    /// kinetic energy, as if the burn were instantaneous. Gravity losses are that scaled by
+    /// [Craft::gravity_loss_factor]. Atmospheric drag is not accounted for
+    pub fn launch_deltav_estimate(&self, planet: &Planet, target_altitude: Real) -> Real {
+        let orbit_radius = planet.radius + target_altitude;
+        // v² / 2 at the orbit plus the potential difference, with v² = μ / r
+        let ideal = (planet.mu() * (2.0 / planet.radius - 1.0 / orbit_radius)).sqrt();
+        ideal * (1.0 + self.gravity_loss_factor)
+    }
+
+    /// Specific angular momentum `r × v` relative to a planet, in m²/s. Positive when the craft
+    /// orbits counterclockwise
+    pub fn specific_angular_momentum(&self, planet: &Planet) -> Real {
+        (self.position - planet.position).perp(&self.relative_velocity(planet))
+    }
+
+    /// Delta-v vector of the burn that circularizes the two-body orbit around a planet at its
+    /// apoapsis or periapsis, to be applied once the craft gets there
+    ///
+    /// Fails with [ApogeeError::NotBound] for the apoapsis of an unbound trajectory
+    pub fn circularization_burn(
+        &self,
+        planet: &Planet,
+        at_apoapsis: bool,
+    ) -> Result<AbiDVec2, ApogeeError> {
+        let mu = planet.mu();
+        let r = self.position - planet.position;
+        let energy = self.specific_orbital_energy(planet);
+        if at_apoapsis && energy >= 0.0 {
+            return Err(ApogeeError::NotBound);
+        }
+        let h = self.specific_angular_momentum(planet);
+        let ecc = self.eccentricity_vector(planet);
+        let e = ecc.magnitude();
+        let periapsis = h * h / (mu * (1.0 + e));
+        let (radius, direction) = if at_apoapsis {
+            (h * h / (mu * (1.0 - e)), -ecc)
+        } else {
+            (periapsis, ecc)
+        };
+        // The apses of a circular orbit are anywhere, burn where the craft is
+        let direction = if e < 1e-12 { r } else { direction }.normalize();
+        // Prograde at the apsis, perpendicular to the radius in the direction of motion
+        let prograde = DVec2::new(-direction.y, direction.x) * h.signum();
+        let apsis_speed = h.abs() / radius;
+        let circular_speed = (mu / radius).sqrt();
+        Ok((prograde * (circular_speed - apsis_speed)).into())
+    }
+
+    /// Two-body orbit around a planet from the current position and speed alone, so it's
+    /// available before the trajectory is predicted
+    pub fn orbital_elements(&self, planet: &Planet) -> OrbitalElements {
+        let r = self.position - planet.position;
+        let ecc = self.eccentricity_vector(planet);
+        let energy = self.specific_orbital_energy(planet);
+        let argument_of_periapsis = math::atan2(ecc.y, ecc.x);
+        // Measured in the direction of motion
+        let true_anomaly = math::atan2(r.y, r.x) - argument_of_periapsis;
+        let true_anomaly = normalize_angle(if self.specific_angular_momentum(planet) < 0.0 {
+            -true_anomaly
+        } else {
+            true_anomaly
+        });
+        OrbitalElements {
+            semi_major_axis: -planet.mu() / (2.0 * energy),
+            eccentricity: ecc.magnitude(),
+            argument_of_periapsis,
+            true_anomaly,
+            period: self.orbital_period(planet).unwrap_or(Real::NAN),
+        }
+    }
+
+    /// Shape of the two-body orbit around a planet, `None` when thrust or drag perturb it too
+    /// much for a conic to be accurate, or when it's too close to parabolic to draw as one.
+    /// [Simulation::conic_render_params] also accounts for the other planets
+    pub fn conic_render_params(&self, planet: &Planet) -> Option<ConicParams> {
+        let perturbation = self.accel_vector()
+            + drag_accel(
+                std::slice::from_ref(planet),
+                &[],
+                self.drag_factor(),
+                self.position,
+                self.speed,
+            );
+        self.conic_params_perturbed(planet, perturbation)
+    }
+
+    /// Farthest distance from a planet's center along the two-body orbit, `None` if the craft
+    /// isn't bound to it. Doesn't need the trajectory, unlike [Craft::apsis_markers]
+    pub fn apoapsis(&self, planet: &Planet) -> Option<Real> {
+        let elements = self.orbital_elements(planet);
+        (elements.eccentricity < 1.0)
+            .then_some(elements.semi_major_axis * (1.0 + elements.eccentricity))
+    }
+
+    /// Closest distance to a planet's center along the two-body orbit, which unbound crafts
+    /// pass too
+    pub fn periapsis(&self, planet: &Planet) -> Real {
+        let h = self.specific_angular_momentum(planet);
+        let e = self.eccentricity_vector(planet).magnitude();
+        h * h / (planet.mu() * (1.0 + e))
+    }
+
+    /// First point where the predicted trajectory reaches a planet's surface, refined between
+    /// the predicted points. `None` if it doesn't within the horizon, or if the craft is already
+    /// below the surface
+    ///
+    /// The planet is taken to stay where it is now, which only matters in [Config::n_body] mode.
+    /// An impact before the first predicted point is reported at that point
+    pub fn predicted_impact(&self, planet: &Planet) -> Option<Impact> {
+        let impact = |vp: &VelPos| Impact {
+            position: vp.pos.into(),
+            speed: (vp.vel - planet.velocity).magnitude(),
+            time: vp.time,
+        };
+        if planet.altitude_of(self.position) < 0.0 {
+            return None;
+        }
+        let first = self.trajectory.front()?;
+        if planet.altitude_of(first.pos) < 0.0 {
+            return Some(impact(first));
+        }
+        let (a, b) = self
+            .trajectory
+            .iter()
+            .zip(self.trajectory.iter().skip(1))
+            .find(|(_, b)| planet.altitude_of(b.pos) < 0.0)?;
+        // Bisects the interpolated segment for where the altitude crosses zero
+        let (mut low, mut high) = (0.0, 1.0);
+        for _ in 0..32 {
+            let mid = (low + high) / 2.0;
+            if planet.altitude_of(hermite(a, b, mid).pos) >= 0.0 {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        Some(impact(&hermite(a, b, high)))
+    }
+
+    /// Dynamic pressure `½ρv²` of the air from a planet's atmosphere, in pascals
+    pub fn dynamic_pressure(&self, planet: &Planet) -> Real {
+        0.5 * planet.density_at(self.position) * self.relative_velocity(planet).magnitude_squared()
+    }
+
+    /// Estimated time until atmospheric drag decays the orbit down to the surface of a planet
+    ///
+    /// Extrapolates the current rate of orbital energy loss, so it's only a rough figure for
+    /// slowly decaying orbits. Returns `None` if there's no drag or the orbit is gaining energy
+    pub fn decay_time_estimate(&self, planet: &Planet) -> Option<Real> {
+        let drag = drag_accel(
+            std::slice::from_ref(planet),
+            &[],
+            self.drag_factor(),
+            self.position,
+            self.speed,
+        );
+        let energy_rate = (drag + self.accel_vector()).dot(&self.relative_velocity(planet));
+        if drag == DVec2::zeros() || energy_rate >= 0.0 {
+            return None;
+        }
+        // Energy of a circular orbit skimming the surface
+        let surface_energy = -planet.mu() / (2.0 * planet.radius);
+        let energy = self.specific_orbital_energy(planet);
+        Some(((energy - surface_energy) / -energy_rate).max(0.0))
+    }
+
+    /// Predicted trajectory as CSV with a `time,x,y,vx,vy` header
+    ///
+    /// Numbers are written with the shortest representation that parses back to the same value
+    pub fn trajectory_csv(&self) -> String {
+        let mut csv = String::from("time,x,y,vx,vy\n");
+        for vp in &self.trajectory {
+            // Writing to a String can't fail
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{}",
+                vp.time, vp.pos.x, vp.pos.y, vp.vel.x, vp.vel.y
+            );
+        }
+        csv
+    }
+
+    /// Interpolated positions of the apoapses and periapses along the predicted trajectory, in
+    /// the order they are passed
+    pub fn apsis_markers(&self) -> Vec<AbiDVec2> {
+        self.apsides.iter().map(|a| a.pos.into()).collect()
+    }
+
+    /// Whether the predicted trajectory crosses itself, meaning the orbit closes into a loop
+    pub fn trajectory_is_closed(&self) -> bool {
+        self.trajectory_self_intersection().is_some()
+    }
+
+    /// First point where the predicted trajectory crosses itself, if any
+    pub fn trajectory_intersection(&self) -> Option<AbiDVec2> {
+        self.trajectory_self_intersection().map(Into::into)
+    }
+}
+
+/// Can call a JS closure over items in the deque
+#[wasm_bindgen]
+pub struct TrajectoryIter {
+    inner: *const VecDeque<VelPos>,
+}
+
+#[wasm_bindgen]
+impl TrajectoryIter {
+    /// Calls the provided JS closure for each element in the buffer
+    ///
+    /// The closure takes x and y coordinates, if any exception is caught the loop is stopped and
+    /// the error is returned
+    pub fn each_position(&self, f: &js_sys::Function) -> Result<(), JsValue> {
+        let this = JsValue::null();
+        for elem in unsafe { &*self.inner } {
+            f.call2(
+                &this,
+                &JsValue::from(elem.pos.x),
+                &JsValue::from(elem.pos.y),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Calls the provided JS closure with each whole predicted state as an [AbiVelPos], stopping
+    /// at the first exception like [TrajectoryIter::each_position]
+    pub fn each_state(&self, f: &js_sys::Function) -> Result<(), JsValue> {
+        let this = JsValue::null();
+        for elem in unsafe { &*self.inner } {
+            f.call1(&this, &AbiVelPos::from(*elem).into())?;
+        }
+        Ok(())
+    }
+
+    /// Like [TrajectoryIter::each_position] with the time each point is predicted for passed
+    /// first, since they aren't evenly spaced with [Config::adaptive_sampling]
+    pub fn each_timed_position(&self, f: &js_sys::Function) -> Result<(), JsValue> {
+        let this = JsValue::null();
+        for elem in unsafe { &*self.inner } {
+            f.call3(
+                &this,
+                &JsValue::from(elem.time),
+                &JsValue::from(elem.pos.x),
+                &JsValue::from(elem.pos.y),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Craft {
+    /// Index of the smallest sphere of influence around a planet other than the heaviest one
+    /// that contains the craft, or else of the planet pulling it the strongest. Planets the
+    /// craft ignores are never picked, `None` without any others
+    pub fn current_soi(&self, planets: &[Planet]) -> Option<usize> {
+        let primary = primary_planet(planets)?;
+        planets
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != primary && !self.ignores(i))
+            .map(|(i, p)| (i, p.soi_radius(&planets[primary])))
+            .filter(|&(i, radius)| (self.position - planets[i].position).magnitude() < radius)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| i)
+            .or_else(|| dominant_planet(planets, &self.ignored_planets, self.position))
+    }
+
+    /// Smallest distance to the center of a planet and when it's reached, scanning the state at
+    /// `time` and then the predicted ones
+    fn closest_approach(
+        &self,
+        ephemeris: &mut Ephemeris,
+        cfg: &Config,
+        time: Time,
+        planet_index: usize,
+    ) -> (Real, Time) {
+        let mut distance_at = |pos: DVec2, time: Time| {
+            (pos - ephemeris.at(cfg, time)[planet_index].position).magnitude()
+        };
+        let mut closest = (distance_at(self.position, time), time);
+        for vp in &self.trajectory {
+            let distance = distance_at(vp.pos, vp.time);
+            if distance < closest.0 {
+                closest = (distance, vp.time);
+            }
+        }
+        closest
+    }
+
+    /// Scans the trajectory segments in order for the first one that crosses an earlier,
+    /// non-adjacent segment
+    fn trajectory_self_intersection(&self) -> Option<DVec2> {
+        let points = &self.trajectory;
+        let segments = points.len().saturating_sub(1);
+        let chunk_len = segments.div_ceil(INTERSECTION_CHUNKS).max(1);
+        let bounds = |i: usize| {
+            let (a, b) = (points[i].pos, points[i + 1].pos);
+            (a.inf(&b), a.sup(&b))
+        };
+        let overlap = |(a_min, a_max): (DVec2, DVec2), (b_min, b_max): (DVec2, DVec2)| {
+            a_min.x <= b_max.x && a_min.y <= b_max.y && b_min.x <= a_max.x && b_min.y <= a_max.y
+        };
+        // Bounds of the segments each chunk holds so far
+        let mut chunks = [(DVec2::zeros(), DVec2::zeros()); INTERSECTION_CHUNKS];
+        for j in 2..segments {
+            // Segment j - 2 just stopped being adjacent, so it joins the candidates
+            let newest = j - 2;
+            let (min, max) = bounds(newest);
+            let chunk = &mut chunks[newest / chunk_len];
+            *chunk = if newest % chunk_len == 0 {
+                (min, max)
+            } else {
+                (chunk.0.inf(&min), chunk.1.sup(&max))
+            };
+            let b = bounds(j);
+            for (c, &chunk) in chunks[..=newest / chunk_len].iter().enumerate() {
+                if !overlap(chunk, b) {
+                    continue;
+                }
+                for i in c * chunk_len..((c + 1) * chunk_len).min(j - 1) {
+                    if !overlap(bounds(i), b) {
+                        continue;
+                    }
+                    if let Some(p) = segment_intersection(
+                        points[i].pos,
+                        points[i + 1].pos,
+                        points[j].pos,
+                        points[j + 1].pos,
+                    ) {
+                        return Some(p);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Velocity in the frame moving along with a planet
+    fn relative_velocity(&self, planet: &Planet) -> DVec2 {
+        self.speed - planet.velocity
+    }
+
+    /// Total craft mass
+    fn mass(&self) -> Real {
+        self.dry_mass + self.fuel_mass
+    }
+
+    /// Returns the craft's acceleration vector
+    fn accel_vector(&self) -> DVec2 {
+        if self.fuel_mass == 0.0 {
+            return DVec2::new(0.0, 0.0);
+        }
+
+        let thrust = self.thrust * self.effective_throttle;
+        let direction = self.thrust_direction.unwrap_or(self.heading);
+        DVec2::new(math::cos(direction), math::sin(direction)) * (thrust / self.mass())
+    }
+
+    /// Tsiolkovsky rocket equation with the given g₀
+    fn deltav_with(&self, standard_gravity: Real) -> Real {
+        let exhaust_vel = self.isp * standard_gravity;
+        let mass_ratio = self.mass() / self.dry_mass;
+        exhaust_vel * math::ln(mass_ratio)
+    }
+
+    /// Compute the consumed fuel from the expended delta-v in the given time
+    fn consume_fuel(&mut self, time: Real, standard_gravity: Real) {
+        // flow_rate = F / (g_0 * Isp)
+
+        let force = self.thrust * self.effective_throttle;
+        let exhaust_velocity = self.isp * standard_gravity;
+        let flow_rate = force / exhaust_velocity;
+        debug!("force {force} N, exhaust velocity {exhaust_velocity} m/s");
+
+        self.fuel_mass = (self.fuel_mass - flow_rate * time).max(0.0)
+    }
+
+    /// Drag deceleration per unit of air density and squared speed, `Cd·A / 2m`
+    fn drag_factor(&self) -> Real {
+        if self.mass() <= 0.0 {
+            return 0.0;
+        }
+        self.drag_coefficient * self.drag_area / (2.0 * self.mass())
+    }
+
+    /// Updates the dynamic pressure history, returning the peak pressure if it just started
+    /// decreasing after rising
+    fn track_dynamic_pressure(&mut self, planets: &[Planet]) -> Option<Real> {
+        let q = felt_planets(planets, &self.ignored_planets)
+            .map(|p| self.dynamic_pressure(p))
+            .sum();
+        let last = std::mem::replace(&mut self.last_dynamic_pressure, q);
+        let was_rising = std::mem::replace(&mut self.dynamic_pressure_rising, q > last);
+        if was_rising && q < last {
+            self.max_q = last;
+            Some(last)
+        } else {
+            None
+        }
+    }
+
+    /// Moves the effective throttle towards the commanded one over `time` seconds
+    fn spool_throttle(&mut self, time: Real) {
+        if self.throttle_response_rate <= 0.0 {
+            self.effective_throttle = self.throttle;
+            return;
+        }
+        let max_change = self.throttle_response_rate * time;
+        let change = (self.throttle - self.effective_throttle).clamp(-max_change, max_change);
+        self.effective_throttle += change;
+    }
+
+    /// Heats the craft by convection with the surrounding air (`∝ ρv³`) and cools it by
+    /// radiation over `time` seconds
+    fn exchange_heat(&mut self, planets: &[Planet], time: Real) {
+        let heat_capacity = self.mass() * CRAFT_SPECIFIC_HEAT;
+        if heat_capacity <= 0.0 {
+            return;
+        }
+        let heating: Real = felt_planets(planets, &self.ignored_planets)
+            .map(|p| {
+                let airspeed = self.relative_speed(p);
+                HEAT_TRANSFER_FRACTION * 0.5 * p.density_at(self.position) * math::powi(airspeed, 3)
+            })
+            .sum();
+        let cooling = HULL_EMISSIVITY
+            * STEFAN_BOLTZMANN
+            * (math::powi(self.temperature(), 4) - math::powi(AMBIENT_TEMPERATURE, 4));
+        let power = (heating - cooling) * self.drag_area;
+        self.heat = (self.heat + power * time / heat_capacity).max(0.0);
+    }
+
+    /// [Craft::conic_render_params] with the acceleration not coming from the planet given
+    fn conic_params_perturbed(&self, planet: &Planet, perturbation: DVec2) -> Option<ConicParams> {
+        let central = planet.gravity_accel_on(self.position).magnitude();
+        if perturbation.magnitude() > CONIC_MAX_PERTURBATION * central {
+            return None;
+        }
+        let elements = self.orbital_elements(planet);
+        let (a, e) = (elements.semi_major_axis, elements.eccentricity);
+        if (e - 1.0).abs() < 1e-6 || !a.is_finite() {
+            return None;
+        }
+        let towards_periapsis = DVec2::new(
+            math::cos(elements.argument_of_periapsis),
+            math::sin(elements.argument_of_periapsis),
+        );
+        // The semi-major axis is negative for hyperbolas, putting the center past the periapsis
+        // on the far side from the planet
+        let center = planet.position - towards_periapsis * (a * e);
+        let hyperbolic = e > 1.0;
+        let rotation = if hyperbolic {
+            normalize_angle(elements.argument_of_periapsis + consts::PI)
+        } else {
+            elements.argument_of_periapsis
+        };
+        Some(ConicParams {
+            center: center.into(),
+            semi_major_axis: a.abs(),
+            semi_minor_axis: a.abs() * (1.0 - e * e).abs().sqrt(),
+            rotation,
+            hyperbolic,
+        })
+    }
+
+    /// Points from a planet towards the periapsis, its length is the eccentricity
+    fn eccentricity_vector(&self, planet: &Planet) -> DVec2 {
+        let r = self.position - planet.position;
+        let (v, mu) = (self.relative_velocity(planet), planet.mu());
+        (r * (v.magnitude_squared() - mu / r.magnitude()) - v * r.dot(&v)) / mu
+    }
+
+    /// Period of the two-body orbit around a planet, `None` if the craft isn't bound to it
+    fn orbital_period(&self, planet: &Planet) -> Option<Real> {
+        let energy = self.specific_orbital_energy(planet);
+        if energy >= 0.0 {
+            return None;
+        }
+        let semi_major_axis = -planet.mu() / (2.0 * energy);
+        Some(consts::TAU * (math::powi(semi_major_axis, 3) / planet.mu()).sqrt())
+    }
+
+    /// How many ticks ahead the craft's trajectory is predicted, none if [Craft::predict] is off
+    fn prediction_steps(&self, cfg: &Config) -> u64 {
+        if self.prediction_disabled {
+            return 0;
+        }
+        self.prediction_steps.unwrap_or(cfg.prediction_steps)
+    }
+
+    /// Makes room for a whole prediction plus the tick being consumed, up to
+    /// [MAX_RESERVED_POINTS], so the trajectory doesn't grow while it's first populated and never
+    /// reallocates in steady-state ticking
+    fn reserve_trajectory(&mut self, cfg: &Config) {
+        let needed = (self.prediction_steps(cfg) as usize)
+            .saturating_add(1)
+            .min(MAX_RESERVED_POINTS);
+        self.trajectory
+            .reserve(needed.saturating_sub(self.trajectory.len()));
+    }
+
+    /// Copy of the craft without its predicted trajectory, which is cheaper to recompute than to
+    /// keep around
+    fn physical_clone(&mut self) -> Craft {
+        let trajectory = std::mem::take(&mut self.trajectory);
+        let powered_trajectory = std::mem::take(&mut self.powered_trajectory);
+        let apsides = std::mem::take(&mut self.apsides);
+        let copy = self.clone();
+        self.trajectory = trajectory;
+        self.powered_trajectory = powered_trajectory;
+        self.apsides = apsides;
+        copy
+    }
+
+    /// Drops the predicted states and apsides after the given time
+    fn truncate_trajectory(&mut self, end: Time) {
+        while self.trajectory.back().is_some_and(|vp| vp.time > end) {
+            self.trajectory.pop_back();
+        }
+        while self.apsides.back().is_some_and(|a| a.time > end) {
+            self.apsides.pop_back();
+        }
+    }
+
+    /// Drops the predicted trajectory along with everything derived from it
+    fn clear_trajectory(&mut self) {
+        self.trajectory.clear();
+        self.powered_trajectory.clear();
+        self.apsides.clear();
+    }
+
+    /// Whether the engine is running or spooling towards a nonzero [Craft::throttle]
+    fn engine_on(&self) -> bool {
+        self.thrust > 0.0
+            && self.fuel_mass > 0.0
+            && (self.throttle > 0.0 || self.effective_throttle > 0.0)
+    }
+
+    /// Recomputes the powered trajectory over `len` ticks after `time`, steering, spooling and
+    /// burning fuel the way [Simulation::tick] does
+    fn populate_powered_trajectory(
+        &mut self,
+        ephemeris: &mut Ephemeris,
+        cfg: &Config,
+        time: Time,
+        len: u64,
+    ) {
+        self.powered_trajectory.clear();
+        if !self.engine_on() {
+            return;
+        }
+        let mut ghost = self.physical_clone();
+        let mut state = VelPos {
+            vel: self.speed,
+            pos: self.position,
+            time,
+            heading: self.heading,
+        };
+        if let Some(heading) = ghost.orbital_heading(ephemeris.at(cfg, time), &state) {
+            state.heading = heading;
+        }
+        for _ in 0..len {
+            let planets = ephemeris.at(cfg, state.time);
+            ghost.heading = state.heading;
+            ghost.spool_throttle(cfg.tick_time);
+            let thrust = ghost.accel_vector();
+            let mut next = tick_step(
+                planets,
+                &ghost.ignored_planets,
+                cfg,
+                ghost.drag_factor(),
+                state,
+                thrust,
+            );
+            ghost.consume_fuel(cfg.tick_time, cfg.standard_gravity);
+            if let Some(heading) = ghost.orbital_heading(ephemeris.at(cfg, next.time), &next) {
+                next.heading = heading;
+            }
+            self.powered_trajectory.push_back(next);
+            state = next;
+        }
+    }
+
+    /// Heading [Craft::heading_mode] asks for at a state among `planets`, `None` when it
+    /// doesn't follow from the craft's own state or the direction is undefined
+    fn orbital_heading(&self, planets: &[Planet], state: &VelPos) -> Option<Real> {
+        let planet =
+            dominant_planet(planets, &self.ignored_planets, state.pos).map(|i| &planets[i]);
+        let planet_velocity = planet.map_or(DVec2::zeros(), |p| p.velocity);
+        let radial = planet.map(|p| state.pos - p.position);
+        let direction = match self.heading_mode {
+            HeadingMode::Fixed | HeadingMode::TargetRelative => None,
+            HeadingMode::Prograde => Some(state.vel - planet_velocity),
+            HeadingMode::Retrograde => Some(planet_velocity - state.vel),
+            HeadingMode::RadialOut => radial,
+            HeadingMode::RadialIn => radial.map(|r| -r),
+        };
+        direction.and_then(heading_along)
+    }
+
+    /// Stores the apsis passed between two consecutive predicted states, if any
+    fn record_apsis(&mut self, planets: &[Planet], prev: &VelPos, state: &VelPos) {
+        let (Some(a), Some(b)) = (
+            dominant_planet(planets, &self.ignored_planets, prev.pos),
+            dominant_planet(planets, &self.ignored_planets, state.pos),
+        ) else {
+            return;
+        };
+        // Distances to different bodies aren't comparable
+        if a != b {
+            return;
+        }
+        if let Some(apsis) = apsis_between(&planets[a], prev, state) {
+            self.apsides.push_back(apsis);
+        }
+    }
+
+    /// Computes or extends the current trajectory until it covers `len` ticks after `time`
+    fn populate_trajectory(
+        &mut self,
+        ephemeris: &mut Ephemeris,
+        cfg: &Config,
+        time: Time,
+        len: u64,
+    ) {
+        let Ok(()) =
+            self.populate_trajectory_with(ephemeris, cfg, time, len, || Ok::<_, Infallible>(()));
+    }
+
+    /// Like [Craft::populate_trajectory], calling `on_step` after every predicted tick and
+    /// stopping early if it fails
+    fn populate_trajectory_with<E>(
+        &mut self,
+        ephemeris: &mut Ephemeris,
+        cfg: &Config,
+        time: Time,
+        len: u64,
+        mut on_step: impl FnMut() -> Result<(), E>,
+    ) -> Result<(), E> {
+        let end = time + len as Time * cfg.tick_time as Time;
+        let mut state = self.trajectory.back().copied().unwrap_or(VelPos {
+            vel: self.speed,
+            pos: self.position,
+            time,
+            heading: self.heading,
+        });
+        // Half a tick of slack so rounding in the time tags can't add an extra step
+        while state.time < end - cfg.tick_time as Time / 2.0 {
+            let planets = ephemeris.at(cfg, state.time);
+            let ignored = &self.ignored_planets;
+            let mut next = coast_step(planets, ignored, cfg, self.drag_factor(), state);
+            if cfg.adaptive_sampling {
+                let substeps = adaptive_substeps(planets, ignored, cfg, &state);
+                for i in 1..substeps {
+                    let s = i as Real / substeps as Real;
+                    self.trajectory.push_back(hermite(&state, &next, s));
+                }
+            }
+            // Interpolated points are left out, their velocities are only approximate
+            self.record_apsis(planets, &state, &next);
+            // The heading the next tick would steer to from this point
+            if let Some(heading) = self.orbital_heading(ephemeris.at(cfg, next.time), &next) {
+                next.heading = heading;
+            }
+            self.trajectory.push_back(next);
+            state = next;
+            on_step()?;
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,70 @@
+//! Leveled diagnostics routed to the matching `console` function
+//!
+//! On native targets messages go to stderr instead, since there's no console to call into
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use wasm_bindgen::prelude::*;
+
+/// Verbosity of the diagnostics, each level includes the ones before it
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Off = 0,
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+
+pub(crate) fn set_level(level: LogLevel) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub(crate) fn enabled(level: LogLevel) -> bool {
+    level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+pub(crate) fn write(level: LogLevel, msg: &str) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use web_sys::console;
+        let msg = JsValue::from_str(msg);
+        match level {
+            LogLevel::Off => {}
+            LogLevel::Error => console::error_1(&msg),
+            LogLevel::Warn => console::warn_1(&msg),
+            LogLevel::Info => console::info_1(&msg),
+            LogLevel::Debug => console::debug_1(&msg),
+        }
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    eprintln!("[{:?}] {}", level, msg);
+}
+
+/// Logs a formatted message at the given level, skipping the formatting if it's filtered out
+macro_rules! log {
+    ($level:expr, $($arg:tt)*) => {
+        if $crate::log::enabled($level) {
+            $crate::log::write($level, &format!($($arg)*));
+        }
+    };
+}
+
+macro_rules! error {
+    ($($arg:tt)*) => { log!($crate::log::LogLevel::Error, $($arg)*) };
+}
+
+macro_rules! warn {
+    ($($arg:tt)*) => { log!($crate::log::LogLevel::Warn, $($arg)*) };
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => { log!($crate::log::LogLevel::Info, $($arg)*) };
+}
+
+macro_rules! debug {
+    ($($arg:tt)*) => { log!($crate::log::LogLevel::Debug, $($arg)*) };
+}
@@ -0,0 +1,59 @@
+//! Transcendental functions and integer powers used by the physics
+//!
+//! With the `deterministic` feature they're computed by `libm` and explicit multiplications
+//! instead of the platform's math library and LLVM's `powi`, whose results can differ in the
+//! last bits between targets and optimization levels. Everything else already agrees: IEEE 754
+//! rounds `+`, `-`, `*`, `/` and `sqrt` exactly, and Rust never fuses them into `mul_add` on its
+//! own. The `simd` feature sums planets in a different order, so lockstep clients have to agree
+//! on it too
+
+use crate::Real;
+
+/// Defines a function forwarding to `libm` with the `deterministic` feature and to the standard
+/// library method otherwise
+macro_rules! forward {
+    ($name:ident($first:ident $(, $arg:ident)*), $f64:ident, $f32:ident) => {
+        #[cfg(all(feature = "deterministic", not(feature = "f32")))]
+        pub(crate) fn $name($first: Real $(, $arg: Real)*) -> Real {
+            libm::$f64($first $(, $arg)*)
+        }
+
+        #[cfg(all(feature = "deterministic", feature = "f32"))]
+        pub(crate) fn $name($first: Real $(, $arg: Real)*) -> Real {
+            libm::$f32($first $(, $arg)*)
+        }
+
+        #[cfg(not(feature = "deterministic"))]
+        pub(crate) fn $name($first: Real $(, $arg: Real)*) -> Real {
+            $first.$name($($arg),*)
+        }
+    };
+}
+
+forward!(exp(x), exp, expf);
+forward!(ln(x), log, logf);
+forward!(sin(x), sin, sinf);
+forward!(cos(x), cos, cosf);
+forward!(powf(x, n), pow, powf);
+forward!(atan2(y, x), atan2, atan2f);
+
+/// `x` raised to a non-negative integer power, squaring from the lowest bit up so every target
+/// goes through the same steps
+#[cfg(feature = "deterministic")]
+pub(crate) fn powi(x: Real, n: u32) -> Real {
+    let (mut result, mut base, mut n) = (1.0, x, n);
+    while n > 0 {
+        if n & 1 == 1 {
+            result *= base;
+        }
+        base *= base;
+        n >>= 1;
+    }
+    result
+}
+
+/// `x` raised to a non-negative integer power
+#[cfg(not(feature = "deterministic"))]
+pub(crate) fn powi(x: Real, n: u32) -> Real {
+    x.powi(n as i32)
+}
@@ -0,0 +1,23 @@
+//! Ready-made real celestial bodies, enabled by the `presets` feature
+
+use wasm_bindgen::prelude::*;
+
+use crate::{AbiDVec2, Planet};
+
+#[wasm_bindgen]
+impl Planet {
+    /// Earth, with its mean radius
+    pub fn earth(pos: AbiDVec2) -> Planet {
+        Planet::new(5.9722e24, 6.371e6, pos)
+    }
+
+    /// Earth's Moon, with its mean radius
+    pub fn moon(pos: AbiDVec2) -> Planet {
+        Planet::new(7.342e22, 1.7374e6, pos)
+    }
+
+    /// Mars, with its mean radius
+    pub fn mars(pos: AbiDVec2) -> Planet {
+        Planet::new(6.4171e23, 3.3895e6, pos)
+    }
+}
@@ -0,0 +1,47 @@
+use crate::Real;
+
+/// Small seeded pseudo-random generator (xorshift64*), so perturbation features are
+/// reproducible without pulling in a dependency
+#[derive(Debug, Clone)]
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        // xorshift gets stuck on a zero state, scramble the seed with a splitmix64 round
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        Self { state: z.max(1) }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Uniformly distributed in `(0, 1]`
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        ((self.next_u64() >> 11) + 1) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Normally distributed with zero mean and the given standard deviation (Box-Muller)
+    ///
+    /// Uses `libm` rather than the platform's math library so the samples are identical across
+    /// targets. Always sampled in `f64`, then rounded to [Real]
+    pub(crate) fn gaussian(&mut self, sigma: Real) -> Real {
+        let (u1, u2) = (self.next_f64(), self.next_f64());
+        let normal = (-2.0 * libm::log(u1)).sqrt() * libm::cos(std::f64::consts::TAU * u2);
+        sigma * normal as Real
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
@@ -0,0 +1,40 @@
+//! Gravity summed over several planets per instruction, for scenes with many planets
+
+#[cfg(feature = "f32")]
+use wide::f32x4 as Lanes;
+#[cfg(not(feature = "f32"))]
+use wide::f64x4 as Lanes;
+
+use crate::{DVec2, Planet, Real, G};
+
+/// Newtonian gravitational acceleration applied by every planet on an object of negligible
+/// mass, four planets at a time
+pub(crate) fn gravity_accel<'a>(planets: impl Iterator<Item = &'a Planet>, pos: DVec2) -> DVec2 {
+    let mut planets = planets.fuse();
+    let (x, y) = (Lanes::splat(pos.x), Lanes::splat(pos.y));
+    let (mut ax, mut ay) = (Lanes::ZERO, Lanes::ZERO);
+    loop {
+        let chunk = [
+            planets.next(),
+            planets.next(),
+            planets.next(),
+            planets.next(),
+        ];
+        let [Some(a), Some(b), Some(c), Some(d)] = chunk else {
+            // Fewer than four planets left over
+            return chunk
+                .iter()
+                .flatten()
+                .map(|p| p.gravity_accel_on(pos))
+                .fold(DVec2::new(ax.reduce_add(), ay.reduce_add()), |a, b| a + b);
+        };
+        let lanes = |f: fn(&Planet) -> Real| Lanes::from([f(a), f(b), f(c), f(d)]);
+        let dx = lanes(|p| p.position.x) - x;
+        let dy = lanes(|p| p.position.y) - y;
+        let dist_sq = dx * dx + dy * dy;
+        // μ / r² along the unit vector d / r
+        let scale = lanes(|p| p.mass) * Lanes::splat(G) / (dist_sq * dist_sq.sqrt());
+        ax += dx * scale;
+        ay += dy * scale;
+    }
+}
@@ -0,0 +1,1146 @@
+//! Test suite for native targets.
+
+use physics::*;
+#[cfg(feature = "f32")]
+use std::f32::consts;
+#[cfg(not(feature = "f32"))]
+use std::f64::consts;
+
+const ORIGIN: AbiDVec2 = AbiDVec2 { x: 0.0, y: 0.0 };
+
+// Tests of orbital accuracy are tuned for double precision and skipped with the `f32` feature
+
+/// Tolerance for angles that should only differ by rounding
+#[cfg(not(feature = "f32"))]
+const EPS: Real = 1e-12;
+#[cfg(feature = "f32")]
+const EPS: Real = 1e-5;
+
+#[test]
+fn planet_from_surface_gravity() {
+    let mars_g = 0.38 * 9.80665;
+    let planet = Planet::from_surface_gravity(mars_g, 3.3895e6, ORIGIN);
+    assert!((planet.surface_gravity() - mars_g).abs() < 1e-12);
+}
+
+/// Angles of the periapses along a packed `[x, y, ...]` trajectory around the origin, refined by
+/// fitting a parabola to the distance around each sampled minimum
+#[cfg(not(feature = "f32"))]
+fn periapsis_angles(points: &[Real]) -> Vec<Real> {
+    let pos: Vec<(Real, Real)> = points.chunks_exact(2).map(|p| (p[0], p[1])).collect();
+    let r: Vec<Real> = pos.iter().map(|(x, y)| x.hypot(*y)).collect();
+    (1..r.len() - 1)
+        .filter(|&i| r[i] < r[i - 1] && r[i] <= r[i + 1])
+        .map(|i| {
+            let offset = 0.5 * (r[i - 1] - r[i + 1]) / (r[i - 1] - 2.0 * r[i] + r[i + 1]);
+            let (from, to) = if offset < 0.0 { (i - 1, i) } else { (i, i + 1) };
+            let t = if offset < 0.0 { 1.0 + offset } else { offset };
+            let angle = |j: usize| pos[j].1.atan2(pos[j].0);
+            let mut delta = angle(to) - angle(from);
+            if delta > consts::PI {
+                delta -= consts::TAU;
+            } else if delta < -consts::PI {
+                delta += consts::TAU;
+            }
+            angle(from) + delta * t
+        })
+        .collect()
+}
+
+#[test]
+#[cfg(not(feature = "f32"))]
+fn relativistic_precession() {
+    // Compact body and tight orbit so the precession is large enough to measure
+    let mass: Real = 1e30;
+    let mu = 6.67430e-11 * mass;
+    let c: Real = 299_792_458.0;
+    let (a, e): (Real, Real) = (mu / (c * c * 1e-3), 0.5);
+    let r_p = a * (1.0 - e);
+    let v_p = (mu * (1.0 + e) / r_p).sqrt();
+    let period = consts::TAU * (a.powi(3) / mu).sqrt();
+    let orbits = 3;
+    let ticks_per_orbit = 20_000;
+    let steps = orbits * ticks_per_orbit + ticks_per_orbit / 2;
+
+    let periapses = |relativistic: bool| {
+        let mut cfg = Config::new(period / ticks_per_orbit as Real, steps as u64);
+        cfg.relativistic = relativistic;
+        let mut sim = Simulation::new(cfg);
+        sim.add_planet(Planet::new(mass, 1.0, ORIGIN));
+        let mut craft = Craft::new();
+        craft.set_position(AbiDVec2 { x: r_p, y: 0.0 });
+        craft.set_speed(AbiDVec2 { x: 0.0, y: v_p });
+        sim.add_craft(craft);
+        periapsis_angles(&sim.trajectory_chunk(0, 0, steps).unwrap())
+    };
+    let newtonian = periapses(false);
+    let relativistic = periapses(true);
+    assert_eq!(newtonian.len(), orbits);
+    assert_eq!(relativistic.len(), orbits);
+
+    // Subtracting the Newtonian run cancels the integrator's own numerical precession
+    let measured = (relativistic[orbits - 1] - newtonian[orbits - 1]) / orbits as Real;
+    let expected = 3.0 * consts::TAU * mu / (c * c * a * (1.0 - e * e));
+    assert!(
+        (measured - expected).abs() < expected * 0.05,
+        "measured {} rad/orbit, expected {}",
+        measured,
+        expected
+    );
+}
+
+#[test]
+fn substepping_keeps_eccentric_orbit() {
+    let mass = 5.972e24;
+    let mu = 6.67430e-11 * mass;
+    let (r_p, e): (Real, Real) = (7.0e6, 0.9);
+    let a = r_p / (1.0 - e);
+    let v_p = (mu * (1.0 + e) / r_p).sqrt();
+    let period = consts::TAU * (a.powi(3) / mu).sqrt();
+    // Far too coarse to resolve the periapsis pass without substepping
+    let ticks = 500;
+
+    let mut cfg = Config::new(period / ticks as Real, 10);
+    cfg.max_substeps = 4096;
+    cfg.substep_accel = 1e-3;
+    let mut sim = Simulation::new(cfg);
+    sim.add_planet(Planet::new(mass, 1.0, ORIGIN));
+    let mut craft = Craft::new();
+    craft.set_position(AbiDVec2 { x: r_p, y: 0.0 });
+    craft.set_speed(AbiDVec2 { x: 0.0, y: v_p });
+    sim.add_craft(craft);
+    assert_eq!(sim.tick_many(ticks), ticks);
+
+    let craft = sim.craft(0).unwrap();
+    let (pos, vel) = (craft.position(), craft.speed());
+    let energy = 0.5 * vel.x.hypot(vel.y).powi(2) - mu / pos.x.hypot(pos.y);
+    let expected = -mu / (2.0 * a);
+    assert!(
+        ((energy - expected) / expected).abs() < 0.01,
+        "energy {} J/kg, expected {}",
+        energy,
+        expected
+    );
+    // Back near the periapsis after one orbit
+    assert!((pos.x - r_p).hypot(pos.y) < 0.1 * a);
+}
+
+/// Circular orbit around an Earth-like planet at the origin
+fn orbiting_sim(cfg: Config) -> Simulation {
+    let mass = 5.972e24;
+    let r = 7.0e6;
+    let mut sim = Simulation::new(cfg);
+    sim.add_planet(Planet::new(mass, 6.371e6, ORIGIN));
+    let mut craft = Craft::new();
+    craft.dry_mass = 1000.0;
+    craft.fuel_mass = 1000.0;
+    craft.isp = 300.0;
+    craft.thrust = 1e4;
+    craft.set_position(AbiDVec2 { x: r, y: 0.0 });
+    craft.set_speed(AbiDVec2 {
+        x: 0.0,
+        y: (6.67430e-11 * mass / r).sqrt(),
+    });
+    sim.add_craft(craft);
+    sim
+}
+
+/// Ticks once and checks the craft ended up exactly where its prediction said, skipping over
+/// the points adaptive sampling interpolates within the tick
+fn assert_tick_follows_prediction(sim: &mut Simulation, tick_time: Time) {
+    // Makes sure the trajectory is populated before the first tick
+    sim.trajectory_chunk(0, 0, 1).unwrap();
+    let craft = sim.craft(0).unwrap();
+    let next = sim.time() + tick_time;
+    let predicted = (0..)
+        .map_while(|i| craft.trajectory_state(i))
+        .find(|vp| vp.time > next - tick_time * 1e-3)
+        .unwrap();
+    sim.tick();
+    let craft = sim.craft(0).unwrap();
+    assert_eq!(predicted.time, sim.time());
+    assert_eq!(predicted.pos.x, craft.position().x);
+    assert_eq!(predicted.pos.y, craft.position().y);
+    assert_eq!(predicted.vel.x, craft.speed().x);
+    assert_eq!(predicted.vel.y, craft.speed().y);
+}
+
+#[test]
+fn coasting_ticks_follow_prediction() {
+    let mut sim = orbiting_sim(Config::new(10.0, 100));
+    for _ in 0..200 {
+        assert_tick_follows_prediction(&mut sim, 10.0);
+    }
+}
+
+#[test]
+fn coasting_ticks_follow_adaptive_prediction() {
+    let mut cfg = Config::new(60.0, 100);
+    cfg.adaptive_sampling = true;
+    let mut sim = orbiting_sim(cfg);
+    for _ in 0..200 {
+        assert_tick_follows_prediction(&mut sim, 60.0);
+    }
+}
+
+#[test]
+fn coasting_after_a_burn_follows_prediction() {
+    let mut sim = orbiting_sim(Config::new(10.0, 100));
+    sim.set_craft_throttle(0, 1.0).unwrap();
+    sim.tick_many(5);
+    sim.set_craft_throttle(0, 0.0).unwrap();
+    for _ in 0..50 {
+        assert_tick_follows_prediction(&mut sim, 10.0);
+    }
+}
+
+#[test]
+fn n_body_ticks_follow_prediction() {
+    let mut cfg = Config::new(10.0, 200);
+    cfg.n_body = true;
+    let mut sim = Simulation::new(cfg);
+    sim.add_planet(Planet::new(5.972e24, 6.4e6, ORIGIN));
+    let mut moon = Planet::new(7.3e22, 1.7e6, AbiDVec2 { x: 3.84e7, y: 0.0 });
+    moon.set_velocity(AbiDVec2 { x: 0.0, y: 3200.0 });
+    sim.add_planet(moon);
+    let mut craft = Craft::new();
+    craft.set_position(AbiDVec2 { x: 3.0e7, y: 0.0 });
+    craft.set_speed(AbiDVec2 { x: 0.0, y: 3000.0 });
+    sim.add_craft(craft);
+
+    // The planets move along the prediction too, so the whole chunk lands on the ticks
+    let predicted = sim.trajectory_chunk(0, 0, 150).unwrap();
+    for point in predicted.chunks(2).skip(9).step_by(10) {
+        assert_eq!(sim.tick_many(10), 10);
+        let pos = sim.craft(0).unwrap().position();
+        assert_eq!(point[0].to_bits(), pos.x.to_bits());
+        assert_eq!(point[1].to_bits(), pos.y.to_bits());
+    }
+    assert_eq!(sim.tick_count(), 150);
+    for _ in 0..50 {
+        assert_tick_follows_prediction(&mut sim, 10.0);
+    }
+}
+
+#[test]
+fn n_body_follows_a_replaced_config() {
+    let sim = |tick_time| {
+        let mut cfg = Config::new(tick_time, 100);
+        cfg.n_body = true;
+        let mut sim = Simulation::new(cfg);
+        sim.add_planet(Planet::new(5.972e24, 6.4e6, ORIGIN));
+        let mut moon = Planet::new(7.3e22, 1.7e6, AbiDVec2 { x: 3.84e7, y: 0.0 });
+        moon.set_velocity(AbiDVec2 { x: 0.0, y: 3200.0 });
+        sim.add_planet(moon);
+        sim.add_craft(Craft::new());
+        sim
+    };
+    let mut replaced = sim(10.0);
+    // Fills the ephemeris with 10 s frames
+    replaced.trajectory_chunk(0, 0, 100).unwrap();
+    replaced.cfg = sim(60.0).cfg;
+    replaced.tick_many(10);
+    let mut fresh = sim(60.0);
+    fresh.tick_many(10);
+    let (a, b) = (
+        replaced.planet(1).unwrap().position(),
+        fresh.planet(1).unwrap().position(),
+    );
+    assert_eq!((a.x, a.y), (b.x, b.y));
+}
+
+/// Eccentricity of a craft's two-body orbit around a planet
+fn eccentricity(craft: &Craft, planet: &Planet) -> Real {
+    let mu = 6.67430e-11 * planet.mass;
+    let energy = craft.specific_orbital_energy(planet);
+    let h = craft.specific_angular_momentum(planet);
+    (1.0 + 2.0 * energy * h * h / (mu * mu)).max(0.0).sqrt()
+}
+
+#[test]
+fn circularization_burn_circularizes() {
+    let planet = Planet::new(5.972e24, 6.371e6, ORIGIN);
+    let mu = 6.67430e-11 * planet.mass;
+    let (r_p, r_a) = (7.0e6, 2.0e7);
+    let a = (r_p + r_a) / 2.0;
+    let v_p = (mu * (2.0 / r_p - 1.0 / a)).sqrt();
+    let v_a = (mu * (2.0 / r_a - 1.0 / a)).sqrt();
+
+    // Clockwise at the apoapsis and counterclockwise at the periapsis, so both senses are covered
+    let cases = [
+        (
+            AbiDVec2 { x: -r_a, y: 0.0 },
+            AbiDVec2 { x: 0.0, y: v_a },
+            true,
+        ),
+        (
+            AbiDVec2 { x: r_p, y: 0.0 },
+            AbiDVec2 { x: 0.0, y: v_p },
+            false,
+        ),
+    ];
+    for (pos, vel, at_apoapsis) in cases {
+        let mut craft = Craft::new();
+        craft.set_position(pos);
+        craft.set_speed(vel);
+        assert!(eccentricity(&craft, &planet) > 0.4);
+        let burn = craft.circularization_burn(&planet, at_apoapsis).unwrap();
+        craft.set_speed(AbiDVec2 {
+            x: vel.x + burn.x,
+            y: vel.y + burn.y,
+        });
+        let e = eccentricity(&craft, &planet);
+        assert!(e < 1e-9, "eccentricity {} after the burn", e);
+    }
+}
+
+#[test]
+fn heading_is_normalized() {
+    use consts::PI;
+    let mut craft = Craft::new();
+    craft.set_heading(7.0 * PI);
+    assert!((-PI..PI).contains(&craft.heading()));
+    // 7π points the same way as π
+    assert!((craft.heading().cos() + 1.0).abs() < EPS);
+    assert!(craft.heading().sin().abs() < EPS);
+
+    craft.set_heading(-2.5 * PI);
+    assert!((craft.heading() + 0.5 * PI).abs() < EPS);
+    craft.set_heading_degrees(-90.0);
+    assert!((craft.heading_degrees() - 270.0).abs() < EPS);
+    // Would round to exactly 360 without wrapping
+    craft.set_heading(-4.4e-16);
+    assert_eq!(craft.heading_degrees(), 0.0);
+}
+
+#[test]
+fn throttle_is_clamped() {
+    let mut craft = Craft::new();
+    craft.set_throttle(2.0);
+    assert_eq!(craft.throttle(), 1.0);
+    craft.set_throttle(-0.5);
+    assert_eq!(craft.throttle(), 0.0);
+    craft.set_throttle(0.25);
+    assert_eq!(craft.throttle(), 0.25);
+}
+
+#[test]
+fn throttle_spools_at_the_response_rate() {
+    let mut sim = orbiting_sim(Config::new(1.0, 10));
+    let mut craft = Craft::new();
+    craft.dry_mass = 1000.0;
+    craft.fuel_mass = 1000.0;
+    craft.isp = 300.0;
+    craft.thrust = 1e4;
+    craft.throttle_response_rate = 0.5;
+    craft.set_position(AbiDVec2 { x: -7.0e6, y: 0.0 });
+    sim.add_craft(craft);
+    sim.set_craft_throttle(0, 1.0).unwrap();
+    sim.set_craft_throttle(1, 1.0).unwrap();
+
+    let effective = |sim: &mut Simulation| {
+        sim.tick();
+        let (instant, spooled) = (sim.craft(0).unwrap(), sim.craft(1).unwrap());
+        assert_eq!(instant.effective_throttle(), 1.0);
+        (spooled.effective_throttle(), spooled.fuel_mass)
+    };
+    // Half the flow of full throttle over the first second
+    assert_eq!(
+        effective(&mut sim),
+        (0.5, 1000.0 - 1e4 * 0.5 / (300.0 * 9.80665))
+    );
+    assert_eq!(effective(&mut sim).0, 1.0);
+    assert_eq!(effective(&mut sim).0, 1.0);
+    sim.set_craft_throttle(1, 0.25).unwrap();
+    assert_eq!(effective(&mut sim).0, 0.5);
+    assert_eq!(effective(&mut sim).0, 0.25);
+}
+
+/// A craft thrusting straight up from the surface of an Earth-like planet with an atmosphere
+fn ascent_sim() -> Simulation {
+    let mut sim = Simulation::new(Config::new(1.0, 10));
+    let mut planet = Planet::new(5.972e24, 6.371e6, ORIGIN);
+    planet.atmosphere_density = 1.225;
+    planet.scale_height = 8500.0;
+    sim.add_planet(planet);
+    let mut craft = Craft::new();
+    craft.dry_mass = 1000.0;
+    craft.fuel_mass = 1000.0;
+    craft.isp = 300.0;
+    craft.thrust = 6e4;
+    craft.drag_coefficient = 0.5;
+    craft.drag_area = 1.0;
+    craft.set_throttle(1.0);
+    sim.add_craft(craft);
+    sim.launch_from_surface(0, 0, 0.0).unwrap();
+    sim
+}
+
+#[test]
+fn max_q_is_the_ascent_peak() {
+    let mut sim = ascent_sim();
+    let planet = sim.planet(0).unwrap();
+    let (pressures, max_qs): (Vec<Real>, Vec<Real>) = (0..200)
+        .map(|_| {
+            sim.tick();
+            let craft = sim.craft(0).unwrap();
+            (craft.dynamic_pressure(&planet), craft.max_q())
+        })
+        .unzip();
+    // Reported on the first tick the pressure drops, with the previous tick's pressure
+    let drop = pressures.windows(2).position(|w| w[1] < w[0]).unwrap() + 1;
+    let peak = pressures[drop - 1];
+    assert!(pressures.iter().all(|&q| q <= peak));
+    assert!(max_qs[..drop].iter().all(|&q| q == 0.0));
+    assert!(max_qs[drop..].iter().all(|&q| q == peak));
+}
+
+#[test]
+#[cfg(not(feature = "f32"))]
+fn orbit_from_state_without_trajectory() {
+    let planet = Planet::new(5.972e24, 6.371e6, ORIGIN);
+    let mu = 6.67430e-11 * planet.mass;
+    let (r_p, r_a) = (7.0e6, 2.0e7);
+    let a = (r_p + r_a) / 2.0;
+    let mut craft = Craft::new();
+    // At the periapsis on the -y side, moving counterclockwise
+    craft.set_position(AbiDVec2 { x: 0.0, y: -r_p });
+    craft.set_speed(AbiDVec2 {
+        x: (mu * (2.0 / r_p - 1.0 / a)).sqrt(),
+        y: 0.0,
+    });
+    assert_eq!(craft.trajectory_len(), 0);
+
+    let elements = craft.orbital_elements(&planet);
+    assert!((elements.semi_major_axis / a - 1.0).abs() < 1e-9);
+    assert!((elements.eccentricity - (r_a - r_p) / (r_a + r_p)).abs() < 1e-9);
+    assert!((elements.argument_of_periapsis + consts::FRAC_PI_2).abs() < 1e-9);
+    assert!(elements.true_anomaly.abs() < 1e-9);
+    let period = consts::TAU * (a.powi(3) / mu).sqrt();
+    assert!((elements.period / period - 1.0).abs() < 1e-9);
+    assert!((craft.apoapsis(&planet).unwrap() / r_a - 1.0).abs() < 1e-9);
+    assert!((craft.periapsis(&planet) / r_p - 1.0).abs() < 1e-9);
+
+    craft.set_speed(AbiDVec2 {
+        x: 1.1 * (2.0 * mu / r_p).sqrt(),
+        y: 0.0,
+    });
+    assert!(craft.apoapsis(&planet).is_none());
+    assert!(craft.orbital_elements(&planet).period.is_nan());
+    assert!((craft.periapsis(&planet) / r_p - 1.0).abs() < 1e-9);
+}
+
+/// Ticks a craft falling straight down onto a planet until it reaches the surface
+fn drop_onto_planet(mode: CollisionMode, restitution: Real) -> Craft {
+    let radius = 6.371e6;
+    let mut sim = Simulation::new(Config::new(1.0, 10));
+    sim.add_planet(Planet::new(5.972e24, radius, ORIGIN));
+    let mut craft = Craft::new();
+    craft.dry_mass = 1000.0;
+    craft.collision_mode = mode;
+    craft.restitution = restitution;
+    craft.set_position(AbiDVec2 {
+        x: radius + 50.0,
+        y: 0.0,
+    });
+    craft.set_speed(AbiDVec2 { x: -100.0, y: 0.0 });
+    sim.add_craft(craft);
+    sim.tick();
+    sim.craft(0).unwrap()
+}
+
+#[test]
+fn collision_modes() {
+    let radius = 6.371e6;
+    let crashed = drop_onto_planet(CollisionMode::Destroy, 0.0);
+    assert!(crashed.destroyed());
+
+    let passed = drop_onto_planet(CollisionMode::PassThrough, 0.0);
+    assert!(!passed.destroyed());
+    assert!(passed.position().x < radius);
+
+    let landed = drop_onto_planet(CollisionMode::Stop, 0.0);
+    assert!(!landed.destroyed());
+    assert!((landed.position().x - radius).abs() < 1e-6);
+    assert_eq!(landed.speed_magnitude(), 0.0);
+
+    let bounced = drop_onto_planet(CollisionMode::Bounce, 0.5);
+    assert!((bounced.position().x - radius).abs() < 1e-6);
+    assert!(bounced.speed().x > 0.0);
+    assert!(bounced.speed().y.abs() < 1e-9);
+}
+
+#[test]
+fn crafts_pass_through_planets_by_default() {
+    assert_eq!(Craft::new().collision_mode, CollisionMode::PassThrough);
+    // Starts below the surface for a moment while the engine spools up
+    let mut sim = Simulation::new(Config::new(1.0, 10));
+    sim.add_planet(Planet::new(5.972e24, 6.371e6, ORIGIN));
+    let mut craft = Craft::new();
+    craft.dry_mass = 1000.0;
+    craft.fuel_mass = 1000.0;
+    craft.isp = 300.0;
+    craft.thrust = 1e5;
+    craft.throttle_response_rate = 0.1;
+    craft.set_throttle(1.0);
+    sim.add_craft(craft);
+    sim.launch_from_surface(0, 0, 0.0).unwrap();
+    sim.tick();
+    assert!(!sim.craft(0).unwrap().destroyed());
+}
+
+#[test]
+#[cfg(not(feature = "f32"))]
+fn launch_inherits_surface_rotation() {
+    let radius = 6.371e6;
+    let mut planet = Planet::new(5.972e24, radius, ORIGIN);
+    planet.rotation_rate = 7.292e-5;
+    let mut sim = Simulation::new(Config::new(1.0, 10));
+    sim.add_planet(planet);
+    sim.add_craft(Craft::new());
+    sim.launch_from_surface(0, 0, consts::FRAC_PI_2).unwrap();
+    let craft = sim.craft(0).unwrap();
+    assert!(craft.position().x.abs() < 1e-6);
+    assert!((craft.position().y - radius).abs() < 1e-6);
+    // Eastward at the top of a counterclockwise spin is -x
+    assert!((craft.speed().x + 7.292e-5 * radius).abs() < 1e-6);
+    assert!(craft.speed().y.abs() < 1e-9);
+    assert!((craft.heading() - consts::FRAC_PI_2).abs() < 1e-12);
+}
+
+#[test]
+fn disabling_prediction_keeps_motion() {
+    let mut cfg = Config::new(60.0, 100);
+    cfg.adaptive_sampling = true;
+    let mut predicted = orbiting_sim(cfg);
+    let mut unpredicted = orbiting_sim(cfg);
+    unpredicted.set_craft_predict(0, false).unwrap();
+    for _ in 0..50 {
+        predicted.tick();
+        unpredicted.tick();
+    }
+    let (a, b) = (predicted.craft(0).unwrap(), unpredicted.craft(0).unwrap());
+    assert!(b.trajectory_is_empty());
+    assert_eq!(a.position().x, b.position().x);
+    assert_eq!(a.position().y, b.position().y);
+    assert_eq!(a.speed().x, b.speed().x);
+    assert_eq!(a.speed().y, b.speed().y);
+
+    unpredicted.set_craft_predict(0, true).unwrap();
+    assert_eq!(
+        unpredicted.craft(0).unwrap().trajectory_len(),
+        a.trajectory_len()
+    );
+}
+
+#[test]
+fn unit_scales_apply_at_the_boundary() {
+    let mut si = orbiting_sim(Config::new(10.0, 10));
+    let mut cfg = Config::new(10.0, 10);
+    cfg.length_scale = 1000.0;
+    cfg.mass_scale = 5.972e24;
+    let mut scaled = orbiting_sim(cfg);
+    let (a, b) = (
+        si.trajectory_chunk(0, 0, 10).unwrap(),
+        scaled.trajectory_chunk(0, 0, 10).unwrap(),
+    );
+    for (a, b) in a.iter().zip(&b) {
+        assert!((a / 1000.0 - b).abs() < 1e-9);
+    }
+
+    let (a, b) = (
+        si.conic_render_params(0, 0).unwrap().unwrap(),
+        scaled.conic_render_params(0, 0).unwrap().unwrap(),
+    );
+    assert!((a.center.x / 1000.0 - b.center.x).abs() < 1e-9);
+    assert!((a.semi_major_axis / 1000.0 - b.semi_major_axis).abs() < 1e-9);
+    assert!((a.semi_minor_axis / 1000.0 - b.semi_minor_axis).abs() < 1e-9);
+
+    // One Earth mass in
+    scaled.set_planet_mass(0, 1.0).unwrap();
+    let g = scaled.gravity_at(AbiDVec2 { x: 7000.0, y: 0.0 });
+    let expected = 6.67430e-11 * 5.972e24 / (7.0e6 as Real).powi(2) / 1000.0;
+    assert!((g.x + expected).abs() < 1e-12);
+}
+
+#[test]
+#[cfg(not(feature = "f32"))]
+fn v_infinity_of_hyperbolic_state() {
+    let planet = Planet::new(5.972e24, 6.371e6, ORIGIN);
+    let r = 7.0e6;
+    let escape = (2.0 * 6.67430e-11 * planet.mass / r).sqrt();
+    let mut craft = Craft::new();
+    craft.set_position(AbiDVec2 { x: r, y: 0.0 });
+    // v² = v_esc² + v∞²
+    craft.set_speed(AbiDVec2 {
+        x: 0.0,
+        y: (escape * escape + (3000.0 as Real).powi(2)).sqrt(),
+    });
+    assert!((craft.v_infinity(&planet).unwrap() - 3000.0).abs() < 1e-6);
+
+    craft.set_speed(AbiDVec2 {
+        x: 0.0,
+        y: 0.9 * escape,
+    });
+    assert!(craft.v_infinity(&planet).is_none());
+}
+
+#[test]
+fn ticks_without_prediction_steps() {
+    let mut sim = orbiting_sim(Config::new(10.0, 0));
+    let mut reference = orbiting_sim(Config::new(10.0, 100));
+    for _ in 0..20 {
+        sim.tick();
+        reference.tick();
+    }
+    let (a, b) = (sim.craft(0).unwrap(), reference.craft(0).unwrap());
+    assert!(a.trajectory_is_empty());
+    assert_eq!(a.position().x, b.position().x);
+    assert_eq!(a.position().y, b.position().y);
+}
+
+#[test]
+fn ticks_empty_simulation() {
+    let mut sim = Simulation::new(Config::new(10.0, 100));
+    sim.tick();
+    let mut craft = Craft::new();
+    craft.dry_mass = 1000.0;
+    craft.set_speed(AbiDVec2 { x: 1.0, y: 2.0 });
+    sim.add_craft(craft);
+    sim.tick_many(3);
+    let craft = sim.craft(0).unwrap();
+    assert!((craft.position().x - 30.0).abs() < 1e-9);
+    assert!((craft.position().y - 60.0).abs() < 1e-9);
+    assert_eq!(sim.time(), 40.0);
+}
+
+#[test]
+fn cancelling_stops_tick_many_early() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let mut sim = orbiting_sim(Config::new(10.0, 0));
+    let (token, finished) = (sim.cancel_token(), Arc::new(AtomicBool::new(false)));
+    // Keeps cancelling from another thread, as tick_many clears the flag when it starts
+    let canceller = {
+        let finished = finished.clone();
+        std::thread::spawn(move || {
+            while !finished.load(Ordering::Relaxed) {
+                token.cancel();
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+        })
+    };
+    let steps = 100_000_000;
+    let done = sim.tick_many(steps);
+    finished.store(true, Ordering::Relaxed);
+    canceller.join().unwrap();
+    assert!(done < steps);
+    assert_eq!(sim.tick_count(), u64::from(done));
+    assert!(sim.cancel_token().cancelled());
+
+    // The next call starts over with the flag cleared
+    assert_eq!(sim.tick_many(3), 3);
+    assert!(!sim.cancel_token().cancelled());
+}
+
+#[test]
+fn prediction_periods_need_a_tick_time() {
+    let mut sim = orbiting_sim(Config::default());
+    assert!(sim.set_prediction_periods(0, 2.0, 0).is_err());
+    sim.set_tick_time(10.0).unwrap();
+    sim.set_prediction_periods(0, 2.0, 0).unwrap();
+    // Two orbits of about 97 minutes
+    let len = sim.craft(0).unwrap().trajectory_len();
+    assert!((1160..1170).contains(&len), "{} points", len);
+}
+
+#[test]
+fn divergence_needs_a_tick_time_and_horizon() {
+    let mut sim = orbiting_sim(Config::default());
+    assert!(sim.divergence(0, 1.0, 600.0).is_err());
+    sim.set_tick_time(10.0).unwrap();
+    for horizon in [0.0, -600.0, Real::INFINITY, Real::NAN] {
+        assert!(sim.divergence(0, 1.0, horizon).is_err());
+    }
+    assert!(sim.divergence(0, 1.0, 600.0).unwrap().is_finite());
+}
+
+#[test]
+fn nan_tick_time_still_ticks() {
+    let mut sim = orbiting_sim(Config::new(Real::NAN, 10));
+    sim.cfg.max_substeps = 8;
+    assert_eq!(sim.tick_many(3), 3);
+    assert_eq!(sim.tick_count(), 3);
+    assert!(sim.trajectory_chunk(0, 0, 1).is_err());
+}
+
+#[test]
+fn short_ticks_late_in_a_run() {
+    // Far enough in for a 60 Hz tick to be lost in the rounding of a single precision clock
+    let mut sim = orbiting_sim(Config::new(1000.0, 10));
+    sim.tick_many(600);
+    let start = sim.time();
+    sim.set_tick_time(1.0 / 60.0).unwrap();
+    sim.tick();
+    // Substeps are shorter still
+    sim.set_tick_time(0.01).unwrap();
+    sim.cfg.max_substeps = 8;
+    sim.cfg.substep_accel = 1e-3;
+    sim.tick_many(10);
+    assert!((sim.time() - start - (1.0 / 60.0 + 0.1)).abs() < 1e-6);
+}
+
+#[test]
+fn coasting_at_the_trajectory_length_boundary() {
+    for steps in [1, 2] {
+        let mut sim = orbiting_sim(Config::new(10.0, steps));
+        for _ in 0..50 {
+            // Fills the buffer exactly up to the horizon plus the tick being consumed
+            sim.trajectory_chunk(0, 0, steps as usize + 1).unwrap();
+            assert_tick_follows_prediction(&mut sim, 10.0);
+            assert_eq!(sim.craft(0).unwrap().trajectory_len(), steps as usize);
+        }
+    }
+}
+
+/// Ticks a burn through an atmosphere, exercising gravity, drag, heating and the thrust
+/// direction, returning the craft's final state
+#[cfg(all(feature = "deterministic", not(feature = "f32")))]
+fn golden_burn() -> [f64; 4] {
+    let mut cfg = Config::new(1.0, 50);
+    cfg.adaptive_sampling = true;
+    let mut planet = Planet::new(5.972e24, 6.371e6, ORIGIN);
+    planet.atmosphere_density = 1.225;
+    planet.scale_height = 8500.0;
+    let mut sim = Simulation::new(cfg);
+    sim.add_planet(planet);
+    let mut craft = Craft::new();
+    craft.dry_mass = 1000.0;
+    craft.fuel_mass = 4000.0;
+    craft.isp = 300.0;
+    craft.thrust = 1.5e5;
+    craft.drag_coefficient = 0.5;
+    craft.drag_area = 2.0;
+    craft.set_position(AbiDVec2 { x: 6.421e6, y: 0.0 });
+    craft.set_speed(AbiDVec2 { x: 0.0, y: 400.0 });
+    craft.set_heading(1.2);
+    craft.set_throttle(1.0);
+    sim.add_craft(craft);
+    sim.tick_many(120);
+    let craft = sim.craft(0).unwrap();
+    let (pos, vel) = (craft.position(), craft.speed());
+    [pos.x, pos.y, vel.x, vel.y]
+}
+
+#[test]
+#[cfg(all(feature = "deterministic", not(feature = "f32")))]
+fn golden_trajectory() {
+    // Recorded from a deterministic build, every target and profile has to reproduce them
+    let expected = [
+        0x4158b04043319444,
+        0x41158a07a405590f,
+        0x4081327c97493019,
+        0x40b23fb70b2753b3,
+    ];
+    assert_eq!(golden_burn().map(f64::to_bits), expected);
+}
+
+#[test]
+fn heading_modes_track_the_orbit() {
+    let mut sim = orbiting_sim(Config::new(10.0, 10));
+    let mut craft = sim.craft(0).unwrap();
+    craft.heading_mode = HeadingMode::Prograde;
+    sim.add_craft(craft);
+    let mut craft = sim.craft(0).unwrap();
+    craft.heading_mode = HeadingMode::RadialIn;
+    sim.add_craft(craft);
+    sim.tick();
+    // Both start at (r, 0) moving along +y, the headings are set from that state
+    let prograde = sim.craft(1).unwrap().heading();
+    let radial_in = sim.craft(2).unwrap().heading();
+    assert!((prograde - consts::FRAC_PI_2).abs() < EPS);
+    assert!((radial_in + consts::PI).abs() < EPS);
+    assert_eq!(sim.craft(0).unwrap().heading(), 0.0);
+}
+
+#[test]
+fn predicted_headings_follow_heading_modes() {
+    let mut sim = orbiting_sim(Config::new(10.0, 10));
+    let mut craft = sim.craft(0).unwrap();
+    craft.heading_mode = HeadingMode::Prograde;
+    sim.add_craft(craft);
+    sim.trajectory_chunk(1, 0, 1).unwrap();
+    for _ in 0..25 {
+        let predicted = sim.craft(1).unwrap().trajectory_state(0).unwrap();
+        // The craft reaches the predicted state, then steers from it on the tick after
+        sim.tick();
+        sim.tick();
+        assert_eq!(sim.craft(1).unwrap().heading(), predicted.heading);
+    }
+    // Far enough along the orbit to have turned away from the starting heading
+    let heading = sim.craft(1).unwrap().heading();
+    assert!((heading - consts::FRAC_PI_2).abs() > 0.3);
+}
+
+#[test]
+fn seeded_ensembles_repeat_bit_for_bit() {
+    let mut sim = orbiting_sim(Config::new(10.0, 100));
+    let bits = |samples: Vec<Real>| samples.into_iter().map(Real::to_bits).collect::<Vec<_>>();
+    sim.set_seed(42);
+    let first = bits(sim.ensemble_closest_approach(0, 0, 8, 100.0, 1.0).unwrap());
+    assert_eq!(first.len(), 8);
+    // The generator moved on, so the next run draws different noise
+    let second = bits(sim.ensemble_closest_approach(0, 0, 8, 100.0, 1.0).unwrap());
+    assert_ne!(first, second);
+
+    sim.reseed();
+    let again = bits(sim.ensemble_closest_approach(0, 0, 8, 100.0, 1.0).unwrap());
+    assert_eq!(first, again);
+    sim.set_seed(42);
+    let again = bits(sim.ensemble_closest_approach(0, 0, 8, 100.0, 1.0).unwrap());
+    assert_eq!(first, again);
+    assert_eq!(
+        second,
+        bits(sim.ensemble_closest_approach(0, 0, 8, 100.0, 1.0).unwrap())
+    );
+}
+
+#[test]
+fn powered_prediction_follows_thrusting_ticks() {
+    let mut sim = orbiting_sim(Config::new(10.0, 40));
+    let mut craft = sim.craft(0).unwrap();
+    craft.heading_mode = HeadingMode::Prograde;
+    sim.add_craft(craft);
+    assert!(sim.craft(1).unwrap().powered_trajectory_state(0).is_none());
+    sim.set_craft_throttle(1, 1.0).unwrap();
+
+    // Runs out of fuel about 30 ticks in and coasts from there
+    let craft = sim.craft(1).unwrap();
+    let predicted: Vec<_> = (0..40)
+        .map(|i| craft.powered_trajectory_state(i).unwrap())
+        .collect();
+    let coasting = sim.trajectory_chunk(1, 39, 1).unwrap();
+    assert!((coasting[0] - predicted[39].pos.x).abs() > 1e3);
+    for vp in predicted {
+        sim.tick();
+        let craft = sim.craft(1).unwrap();
+        assert_eq!(vp.time, sim.time());
+        assert_eq!(vp.pos.x.to_bits(), craft.position().x.to_bits());
+        assert_eq!(vp.pos.y.to_bits(), craft.position().y.to_bits());
+        assert_eq!(vp.vel.x.to_bits(), craft.speed().x.to_bits());
+        assert_eq!(vp.vel.y.to_bits(), craft.speed().y.to_bits());
+    }
+    assert_eq!(sim.craft(1).unwrap().fuel_mass, 0.0);
+
+    sim.set_craft_throttle(1, 0.0).unwrap();
+    sim.tick();
+    assert!(sim.craft(1).unwrap().powered_trajectory_state(0).is_none());
+}
+
+/// Seconds to fall from rest at `r0` down to `r` towards a point mass, from the radial Kepler
+/// equation
+fn free_fall_time(mu: Real, r0: Real, r: Real) -> Real {
+    let x = r / r0;
+    (r0.powi(3) / (2.0 * mu)).sqrt() * ((x * (1.0 - x)).sqrt() + x.sqrt().acos())
+}
+
+#[test]
+fn orbit_status_reports_impact_escape_and_stable() {
+    let mut sim = orbiting_sim(Config::new(10.0, 100));
+    sim.tick_many(5);
+    let stable = sim.orbit_status(0).unwrap();
+    assert_eq!(stable.kind, OrbitStatusKind::Stable);
+    assert!(stable.time.is_nan());
+
+    // Dropped from rest, it's reported at the first predicted point under the surface
+    let mu = 6.67430e-11 * 5.972e24;
+    let mut craft = Craft::new();
+    craft.set_position(AbiDVec2 { x: 7.0e6, y: 0.0 });
+    sim.add_craft(craft.clone());
+    let impact = sim.orbit_status(1).unwrap();
+    assert_eq!(impact.kind, OrbitStatusKind::WillImpact);
+    let fall = sim.time() + free_fall_time(mu, 7.0e6, 6.371e6) as Time;
+    assert!(impact.time > fall && impact.time <= fall + 10.0);
+    assert_eq!(impact.time % 10.0, 0.0);
+
+    // Past escape velocity from the start, so at the first predicted point
+    craft.set_speed(AbiDVec2 {
+        x: 0.0,
+        y: 1.01 * (2.0 * mu / 7.0e6).sqrt(),
+    });
+    sim.add_craft(craft);
+    let escape = sim.orbit_status(2).unwrap();
+    assert_eq!(escape.kind, OrbitStatusKind::WillEscape);
+    assert_eq!(escape.time, sim.time() + 10.0);
+}
+
+#[test]
+fn predicted_impact_matches_free_fall() {
+    let (mass, radius, r0) = (5.972e24, 6.371e6, 7.0e6);
+    let mu = 6.67430e-11 * mass;
+    let mut sim = Simulation::new(Config::new(1.0, 500));
+    sim.add_planet(Planet::new(mass, radius, ORIGIN));
+    let mut craft = Craft::new();
+    craft.set_position(AbiDVec2 { x: r0, y: 0.0 });
+    sim.add_craft(craft);
+    sim.trajectory_chunk(0, 0, 500).unwrap();
+
+    let impact = sim
+        .craft(0)
+        .unwrap()
+        .predicted_impact(&sim.planet(0).unwrap())
+        .unwrap();
+    assert_eq!(impact.position.y, 0.0);
+    assert!((impact.position.x - radius).abs() < radius * EPS * 10.0);
+    let speed = (2.0 * mu * (1.0 / radius - 1.0 / r0)).sqrt();
+    assert!((impact.speed - speed).abs() < speed * 1e-4);
+    // Semi-implicit Euler runs half a tick ahead of the exact fall
+    let fall = free_fall_time(mu, r0, radius) as Time;
+    assert!((impact.time - (fall - 0.5)).abs() < 1e-2);
+}
+
+#[test]
+fn closed_orbits_cross_themselves() {
+    // Just over one period of the circular orbit, whose slight drift crosses the first lap
+    let mut sim = orbiting_sim(Config::new(10.0, 600));
+    sim.trajectory_chunk(0, 0, 600).unwrap();
+    let craft = sim.craft(0).unwrap();
+    assert!(craft.trajectory_is_closed());
+    let crossing = craft.trajectory_intersection().unwrap();
+    assert!((crossing.x.hypot(crossing.y) - 7.0e6).abs() < 1e4);
+
+    let mu: Real = 6.67430e-11 * 5.972e24;
+    let mut escaping = orbiting_sim(Config::new(10.0, 600));
+    let mut craft = Craft::new();
+    craft.set_position(AbiDVec2 { x: 7.0e6, y: 0.0 });
+    craft.set_speed(AbiDVec2 {
+        x: 0.0,
+        y: 1.2 * (2.0 * mu / 7.0e6).sqrt(),
+    });
+    escaping.add_craft(craft);
+    escaping.trajectory_chunk(1, 0, 600).unwrap();
+    let craft = escaping.craft(1).unwrap();
+    assert!(!craft.trajectory_is_closed());
+    assert!(craft.trajectory_intersection().is_none());
+}
+
+#[test]
+fn soi_events_predict_a_new_craft() {
+    let mut sim = Simulation::new(Config::new(100.0, 200));
+    sim.add_planet(Planet::new(5.972e24, 6.371e6, ORIGIN));
+    sim.add_planet(Planet::new(
+        7.35e22,
+        1.737e6,
+        AbiDVec2 { x: 3.84e8, y: 0.0 },
+    ));
+    let mut craft = Craft::new();
+    // 50 000 km from the moon, well inside its sphere, heading out towards the Earth
+    craft.set_position(AbiDVec2 { x: 3.34e8, y: 0.0 });
+    craft.set_speed(AbiDVec2 { x: -2000.0, y: 0.0 });
+    sim.add_craft(craft);
+    let events = sim.soi_events(0).unwrap();
+    assert_eq!(events.len(), 1);
+    let exit = events[0];
+    assert_eq!(exit.planet, 1);
+    assert!(!exit.is_entry);
+    // The edge is about 16 000 km further at 2 km/s
+    assert!(
+        (7500.0..8500.0).contains(&exit.time),
+        "left at {} s",
+        exit.time
+    );
+    // The same once the prediction exists
+    let again = sim.soi_events(0).unwrap();
+    assert_eq!(again.len(), 1);
+    assert_eq!(again[0].time, exit.time);
+}
+
+#[test]
+fn fuel_used_since_mark() {
+    let mut sim = orbiting_sim(Config::new(10.0, 10));
+    assert!(sim.craft(0).unwrap().fuel_used_since_mark().is_nan());
+    sim.mark_craft_fuel(0).unwrap();
+    sim.set_craft_throttle(0, 1.0).unwrap();
+    let before = sim.craft(0).unwrap().fuel_mass;
+    sim.tick();
+    let craft = sim.craft(0).unwrap();
+    assert!(craft.fuel_used_since_mark() > 0.0);
+    assert_eq!(craft.fuel_used_since_mark(), before - craft.fuel_mass);
+}
+
+#[test]
+fn tick_count_follows_ticks_and_undo() {
+    let mut sim = orbiting_sim(Config::new(10.0, 10));
+    assert_eq!(sim.tick_count(), 0);
+    sim.tick();
+    assert_eq!(sim.tick_many(5), 5);
+    assert_eq!(sim.tick_count(), 6);
+    assert!(sim.undo());
+    assert_eq!(sim.tick_count(), 5);
+}
+
+#[test]
+fn undo_restores_the_state_before_a_burn() {
+    let mut sim = orbiting_sim(Config::new(10.0, 100));
+    sim.set_craft_throttle(0, 1.0).unwrap();
+    let trajectory = sim.trajectory_chunk(0, 0, 100).unwrap();
+    let before = sim.craft(0).unwrap();
+    sim.tick();
+    assert!(sim.craft(0).unwrap().fuel_mass < before.fuel_mass);
+    assert_ne!(sim.trajectory_chunk(0, 0, 100).unwrap(), trajectory);
+
+    assert!(sim.undo());
+    let after = sim.craft(0).unwrap();
+    assert_eq!(sim.time(), 0.0);
+    assert_eq!(after.position().x.to_bits(), before.position().x.to_bits());
+    assert_eq!(after.position().y.to_bits(), before.position().y.to_bits());
+    assert_eq!(after.speed().x.to_bits(), before.speed().x.to_bits());
+    assert_eq!(after.speed().y.to_bits(), before.speed().y.to_bits());
+    assert_eq!(after.fuel_mass.to_bits(), before.fuel_mass.to_bits());
+    // Recomputed from the restored state rather than kept from the burn
+    let recomputed = sim.trajectory_chunk(0, 0, 100).unwrap();
+    assert_eq!(recomputed.len(), trajectory.len());
+    for (a, b) in trajectory.iter().zip(&recomputed) {
+        assert_eq!(a.to_bits(), b.to_bits());
+    }
+}
+
+#[test]
+fn trajectory_chunk_rejects_non_positive_tick_time() {
+    for tick_time in [0.0, -1.0] {
+        let mut sim = orbiting_sim(Config::new(tick_time, 10));
+        assert_eq!(
+            sim.trajectory_chunk(0, 0, 5),
+            Err(ApogeeError::InvalidConfig("tick time must be positive"))
+        );
+    }
+}
+
+#[test]
+fn ignoring_a_planet_matches_removing_it() {
+    let mut cfg = Config::new(10.0, 20);
+    cfg.adaptive_sampling = true;
+    let mut without = orbiting_sim(cfg);
+    let mut ignoring = orbiting_sim(cfg);
+    let mut moon = Planet::new(7.3e22, 1.7e6, AbiDVec2 { x: 1.0e7, y: 0.0 });
+    moon.atmosphere_density = 1.0;
+    moon.scale_height = 1e5;
+    ignoring.add_planet(moon);
+    ignoring.set_planet_ignored(0, 1, true).unwrap();
+    for _ in 0..20 {
+        without.tick();
+        ignoring.tick();
+    }
+    let (a, b) = (
+        without.trajectory_chunk(0, 0, 20).unwrap(),
+        ignoring.trajectory_chunk(0, 0, 20).unwrap(),
+    );
+    assert_eq!(a, b);
+    let (a, b) = (without.craft(0).unwrap(), ignoring.craft(0).unwrap());
+    assert_eq!(a.position().x, b.position().x);
+    assert_eq!(a.position().y, b.position().y);
+    let mut breakdown = without.gravity_breakdown(0).unwrap();
+    breakdown.extend([0.0, 0.0]);
+    assert_eq!(ignoring.gravity_breakdown(0).unwrap(), breakdown);
+
+    // Inside the moon's sphere of influence, which doesn't count while it's ignored
+    let mut craft = ignoring.craft(0).unwrap();
+    craft.set_position(AbiDVec2 { x: 1.0e7, y: 1.0e6 });
+    assert_eq!(
+        craft.current_soi(&[ignoring.planet(0).unwrap(), ignoring.planet(1).unwrap()]),
+        Some(0)
+    );
+    craft.set_planet_ignored(1, false);
+    assert_eq!(
+        craft.current_soi(&[ignoring.planet(0).unwrap(), ignoring.planet(1).unwrap()]),
+        Some(1)
+    );
+}
+
+#[test]
+fn planets_stop_outside_n_body() {
+    let mut moon = Planet::new(7.3e22, 1.7e6, AbiDVec2 { x: 3.84e7, y: 0.0 });
+    moon.set_velocity(AbiDVec2 { x: 0.0, y: 3200.0 });
+    let mut fixed = orbiting_sim(Config::new(10.0, 100));
+    fixed.add_planet(moon.clone());
+    assert_eq!(fixed.planet(1).unwrap().velocity().y, 0.0);
+
+    let mut cfg = Config::new(10.0, 100);
+    cfg.n_body = true;
+    let mut sim = orbiting_sim(cfg);
+    sim.add_planet(moon);
+    sim.tick_many(10);
+    assert!(sim.planet(1).unwrap().velocity().y > 0.0);
+    sim.cfg.n_body = false;
+    sim.tick();
+    let moon = sim.planet(1).unwrap();
+    assert_eq!((moon.velocity().x, moon.velocity().y), (0.0, 0.0));
+    for _ in 0..10 {
+        assert_tick_follows_prediction(&mut sim, 10.0);
+    }
+    assert_eq!(sim.planet(1).unwrap().position().x, moon.position().x);
+}
+
+#[test]
+#[cfg(not(feature = "f32"))]
+fn moving_planet_is_a_moving_frame() {
+    // The same low orbit through a thin atmosphere, around a planet at rest and one drifting
+    // at 30 km/s
+    let run = |drift: Real| {
+        let mut cfg = Config::new(10.0, 10);
+        cfg.n_body = true;
+        let mut sim = Simulation::new(cfg);
+        let (mass, radius) = (5.972e24, 6.371e6);
+        let mut planet = Planet::new(mass, radius, ORIGIN);
+        planet.set_velocity(AbiDVec2 { x: drift, y: 0.0 });
+        planet.atmosphere_density = 1.225;
+        planet.scale_height = 5e4;
+        sim.add_planet(planet);
+        let r = radius + 4e5;
+        let mut craft = Craft::new();
+        craft.dry_mass = 1000.0;
+        craft.drag_coefficient = 2.0;
+        craft.drag_area = 0.01;
+        craft.set_position(AbiDVec2 { x: r, y: 0.0 });
+        craft.set_speed(AbiDVec2 {
+            x: drift,
+            y: (6.67430e-11 * mass / r).sqrt(),
+        });
+        sim.add_craft(craft);
+        sim.tick_many(50);
+        (sim.craft(0).unwrap(), sim.planet(0).unwrap())
+    };
+    let (resting, resting_planet) = run(0.0);
+    let (moving, planet) = run(3e4);
+
+    let offset = |a: AbiDVec2, b: AbiDVec2| (a.x - b.x, a.y - b.y);
+    let (a, b) = (
+        offset(resting.position(), resting_planet.position()),
+        offset(moving.position(), planet.position()),
+    );
+    assert!((a.0 - b.0).hypot(a.1 - b.1) < 1e-3);
+    let (a, b) = (resting.speed(), offset(moving.speed(), planet.velocity()));
+    assert!((a.x - b.0).hypot(a.y - b.1) < 1e-6);
+
+    let (a, b) = (
+        resting.orbital_elements(&resting_planet),
+        moving.orbital_elements(&planet),
+    );
+    // Started circular, drag only lowers the orbit a little
+    assert!(b.eccentricity < 0.1, "eccentricity {}", b.eccentricity);
+    assert!((a.eccentricity - b.eccentricity).abs() < 1e-9);
+    assert!((a.semi_major_axis / b.semi_major_axis - 1.0).abs() < 1e-9);
+    assert!(moving.apoapsis(&planet).is_some());
+    assert!(moving.v_infinity(&planet).is_none());
+
+    // Drag and heating see the air moving along with the planet
+    let (a, b) = (
+        resting.dynamic_pressure(&resting_planet),
+        moving.dynamic_pressure(&planet),
+    );
+    assert!(a > 0.0);
+    assert!((a / b - 1.0).abs() < 1e-9);
+    assert!(resting.temperature() > 290.0);
+    assert!((resting.temperature() - moving.temperature()).abs() < 1e-6);
+}